@@ -0,0 +1,25 @@
+use std::env;
+
+
+// Picks the SIMD kernels compiled in from the target: the x86 ones on x86_64, chosen at
+// run time, NEON on aarch64, and only the portable scalar ones everywhere else or when
+// the scalar feature forces them.
+fn main() {
+    println!("cargo::rustc-check-cfg=cfg(simd, values(\"x86\", \"neon\", \"none\"))");
+
+    let arch = env::var("CARGO_CFG_TARGET_ARCH",).unwrap_or_default();
+    let features = env::var("CARGO_CFG_TARGET_FEATURE",).unwrap_or_default();
+    let has = |feature: &str| features.split(',',).any(|f| f == feature,);
+
+    let simd = if env::var_os("CARGO_FEATURE_SCALAR",).is_some() {
+        "none"
+    } else if arch == "x86_64" {
+        "x86"
+    } else if arch == "aarch64" && has("neon",) {
+        "neon"
+    } else {
+        "none"
+    };
+
+    println!("cargo::rustc-cfg=simd=\"{}\"", simd);
+}
@@ -0,0 +1,235 @@
+use std::sync::OnceLock;
+#[cfg(simd = "x86")]
+use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+#[cfg(simd = "neon")]
+use std::arch::asm;
+
+
+// Instruction sets the search kernels are written for, best first. The x86 ones are
+// all compiled in and picked at run time, NEON is part of every aarch64 target.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    Avx512,
+    Avx2,
+    Sse41,
+    Neon,
+    Scalar,
+}
+
+const BACKENDS: [Backend; 5] =
+    [Backend::Avx512, Backend::Avx2, Backend::Sse41, Backend::Neon, Backend::Scalar,];
+
+
+impl Backend {
+    // Whether the kernels of the backend are compiled in and the CPU runs them
+    #[must_use]
+    pub fn is_supported(self,) -> bool {
+        match self {
+            #[cfg(simd = "x86")]
+            | Self::Avx512 => {
+                is_x86_feature_detected!("avx512f")
+                    && is_x86_feature_detected!("avx512bw")
+                    && is_x86_feature_detected!("popcnt")
+            },
+            #[cfg(simd = "x86")]
+            | Self::Avx2 => {
+                is_x86_feature_detected!("avx2") && is_x86_feature_detected!("popcnt")
+            },
+            #[cfg(simd = "x86")]
+            | Self::Sse41 => is_x86_feature_detected!("sse4.1"),
+            #[cfg(simd = "neon")]
+            | Self::Neon => true,
+            | Self::Scalar => true,
+            | _ => false,
+        }
+    }
+
+    // Supported backends, best first
+    #[must_use]
+    pub fn supported() -> Vec<Self,> {
+        BACKENDS.into_iter().filter(|backend| backend.is_supported(),).collect()
+    }
+
+    #[must_use]
+    pub fn detect() -> Self {
+        BACKENDS
+            .into_iter()
+            .find(|backend| backend.is_supported(),)
+            .unwrap_or(Self::Scalar,)
+    }
+
+    // Backend of the free search functions, detected on first use
+    #[must_use]
+    #[inline(always)]
+    pub fn current() -> Self {
+        static CURRENT: OnceLock<Backend,> = OnceLock::new();
+        *CURRENT.get_or_init(Self::detect,)
+    }
+}
+
+
+// Marker of a backend, which the kernels are generic over
+pub trait Isa {
+    // Child prefetched ahead of the descent, out of the B + 1 children of a node.
+    fn prefetch_offset(block: usize,) -> usize;
+
+    unsafe fn prefetch(ptr: *const i8,);
+}
+
+pub struct Scalar;
+#[cfg(simd = "x86")]
+pub struct Sse41;
+#[cfg(simd = "x86")]
+pub struct Avx2;
+#[cfg(simd = "x86")]
+pub struct Avx512;
+#[cfg(simd = "neon")]
+pub struct Neon;
+
+// No portable prefetch, the hardware prefetchers are left alone
+impl Isa for Scalar {
+    #[inline(always)]
+    fn prefetch_offset(_block: usize,) -> usize {
+        1
+    }
+
+    #[inline(always)]
+    unsafe fn prefetch(_ptr: *const i8,) {}
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_isa_x86 {
+    ($($isa:ty),*) => {$(
+        impl Isa for $isa {
+            #[inline(always)]
+            fn prefetch_offset(block: usize,) -> usize {
+                1 + block / 2
+            }
+
+            #[inline(always)]
+            unsafe fn prefetch(ptr: *const i8,) {
+                unsafe { _mm_prefetch(ptr, _MM_HINT_T0,) };
+            }
+        }
+    )*};
+}
+
+#[cfg(simd = "x86")]
+impl_isa_x86!(Sse41, Avx2, Avx512);
+
+#[cfg(simd = "neon")]
+impl Isa for Neon {
+    #[inline(always)]
+    fn prefetch_offset(_block: usize,) -> usize {
+        1
+    }
+
+    #[inline(always)]
+    unsafe fn prefetch(ptr: *const i8,) {
+        unsafe { asm!("prfm pldl1keep, [{}]", in(reg) ptr) };
+    }
+}
+
+
+// Runs f with the features of a backend enabled: f is inlined, along with the kernels
+// it calls, into a function compiled for them.
+#[cfg(simd = "x86")]
+#[target_feature(enable = "avx512f,avx512bw,popcnt")]
+pub unsafe fn with_avx512<R,>(f: impl FnOnce() -> R,) -> R {
+    f()
+}
+
+#[cfg(simd = "x86")]
+#[target_feature(enable = "avx2,popcnt")]
+pub unsafe fn with_avx2<R,>(f: impl FnOnce() -> R,) -> R {
+    f()
+}
+
+#[cfg(simd = "x86")]
+#[target_feature(enable = "sse4.1")]
+pub unsafe fn with_sse41<R,>(f: impl FnOnce() -> R,) -> R {
+    f()
+}
+
+// Evaluates body with the kernels of backend, $isa being the marker of the backend.
+// Backends which are not compiled in panic, they are never supported.
+macro_rules! dispatch {
+    ($backend:expr, $isa:ident => $body:expr) => {
+        match $backend {
+            #[cfg(simd = "x86")]
+            | $crate::backend::Backend::Avx512 => {
+                type $isa = $crate::backend::Avx512;
+                // The unsafe blocks of body are nested in this one
+                #[allow(unused_unsafe)]
+                let res = unsafe {
+                    $crate::backend::with_avx512(
+                        #[inline(always)]
+                        || $body,
+                    )
+                };
+                res
+            },
+            #[cfg(simd = "x86")]
+            | $crate::backend::Backend::Avx2 => {
+                type $isa = $crate::backend::Avx2;
+                // The unsafe blocks of body are nested in this one
+                #[allow(unused_unsafe)]
+                let res = unsafe {
+                    $crate::backend::with_avx2(
+                        #[inline(always)]
+                        || $body,
+                    )
+                };
+                res
+            },
+            #[cfg(simd = "x86")]
+            | $crate::backend::Backend::Sse41 => {
+                type $isa = $crate::backend::Sse41;
+                // The unsafe blocks of body are nested in this one
+                #[allow(unused_unsafe)]
+                let res = unsafe {
+                    $crate::backend::with_sse41(
+                        #[inline(always)]
+                        || $body,
+                    )
+                };
+                res
+            },
+            #[cfg(simd = "neon")]
+            | $crate::backend::Backend::Neon => {
+                type $isa = $crate::backend::Neon;
+                $body
+            },
+            | $crate::backend::Backend::Scalar => {
+                type $isa = $crate::backend::Scalar;
+                $body
+            },
+            | backend => panic!("{:?} kernels are not compiled in", backend),
+        }
+    };
+}
+
+pub(crate) use dispatch;
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let supported = Backend::supported();
+
+        assert_eq!(supported.first(), Some(&Backend::detect()));
+        assert_eq!(supported.last(), Some(&Backend::Scalar));
+        assert_eq!(Backend::current(), Backend::detect());
+        assert!(supported.iter().all(|backend| backend.is_supported()));
+
+        #[cfg(simd = "x86")]
+        if is_x86_feature_detected!("avx2") {
+            assert!(supported.contains(&Backend::Avx2));
+        }
+        #[cfg(simd = "none")]
+        assert_eq!(supported, [Backend::Scalar]);
+    }
+}
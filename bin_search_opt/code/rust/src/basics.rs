@@ -1,6 +1,28 @@
 use std::borrow::Borrow;
 
 
+// Keys that can be placed on a line, for the searches that predict positions from
+// key values instead of only comparing them
+pub trait Numeric: Copy + PartialOrd {
+    fn to_f64(self,) -> f64;
+}
+
+macro_rules! impl_numeric {
+    ($($t:ty),*) => {
+        $(
+            impl Numeric for $t {
+                #[inline(always)]
+                fn to_f64(self,) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+impl_numeric!(i8, u8, i16, u16, i32, u32, i64, u64, usize, f32, f64);
+
+
 #[must_use]
 #[inline(always)]
 pub fn lower_bound<B, T,>(data: &[B], target: &T,) -> usize
@@ -24,7 +46,6 @@ where
     right as usize
 }
 
-#[expect(unused)]
 #[must_use]
 #[inline(always)]
 pub fn upper_bound<B, T,>(data: &[B], target: &T,) -> usize
@@ -49,8 +70,308 @@ where
 }
 
 
+// Side of the hint on which the answer is expected
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Forward,
+    Backward,
+}
+
+// Galloping from the hint costs O(log d) for an answer at distance d of it. An answer
+// on the other side of the hint is still found, by a binary search of that side.
+macro_rules! impl_bound_exponential {
+    ($(#[$attr:meta])* $name:ident, $base:ident, $go_right:ident) => {
+        $(#[$attr])*
+        #[must_use]
+        #[inline(always)]
+        pub fn $name<B, T,>(data: &[B], target: &T, hint: usize, direction: Direction,) -> usize
+        where
+            B: Borrow<T,>,
+            T: PartialOrd + ?Sized,
+        {
+            let n = data.len();
+            let hint = hint.min(n,);
+
+            match direction {
+                Direction::Forward => {
+                    if hint > 0 && !data[hint - 1].borrow().$go_right(target,) {
+                        return $base(&data[..hint], target,);
+                    }
+
+                    // Everything before lo goes right
+                    let mut lo = hint;
+                    let mut step = 1;
+
+                    while lo + step <= n {
+                        let probe = lo + step - 1;
+                        if !data[probe].borrow().$go_right(target,) {
+                            return lo + $base(&data[lo..probe], target,);
+                        }
+
+                        lo = probe + 1;
+                        step *= 2;
+                    }
+
+                    lo + $base(&data[lo..], target,)
+                }
+                Direction::Backward => {
+                    if hint < n && data[hint].borrow().$go_right(target,) {
+                        return hint + 1 + $base(&data[hint + 1..], target,);
+                    }
+
+                    // Nothing from hi onwards goes right
+                    let mut hi = hint;
+                    let mut step = 1;
+
+                    while step <= hi {
+                        let probe = hi - step;
+                        if data[probe].borrow().$go_right(target,) {
+                            return probe + 1 + $base(&data[probe + 1..hi], target,);
+                        }
+
+                        hi = probe;
+                        step *= 2;
+                    }
+
+                    $base(&data[..hi], target,)
+                }
+            }
+        }
+    };
+}
+
+impl_bound_exponential!(lower_bound_exponential, lower_bound, lt);
+impl_bound_exponential!(upper_bound_exponential, upper_bound, le);
+
+
+// Ranks of the first element not less than target and of the first one greater than
+// it: the elements equal to target lie between both.
+#[must_use]
+#[inline(always)]
+pub fn equal_range<B, T,>(data: &[B], target: &T,) -> (usize, usize,)
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let lower = lower_bound(data, target,);
+
+    // The duplicates follow the lower bound, galloping over them costs O(log count)
+    (lower, upper_bound_exponential(data, target, lower, Direction::Forward,),)
+}
+
+#[must_use]
+#[inline(always)]
+pub fn count<B, T,>(data: &[B], target: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let (lower, upper,) = equal_range(data, target,);
+    upper - lower
+}
+
+// Number of elements in the half-open range [lo, hi)
+#[must_use]
+#[inline(always)]
+pub fn count_in_range<B, T,>(data: &[B], lo: &T, hi: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    lower_bound(data, hi,).saturating_sub(lower_bound(data, lo,),)
+}
+
+
+// Sorted queries split the data: the answers of the queries before the middle one are
+// before its answer, and those of the queries after it are after.
+macro_rules! impl_bound_sorted_batch {
+    ($(#[$attr:meta])* $name:ident, $base:ident) => {
+        $(#[$attr])*
+        pub fn $name<B, T,>(data: &[B], targets: &[T], ranks: &mut [usize],)
+        where
+            B: Borrow<T,>,
+            T: PartialOrd,
+        {
+            assert_eq!(targets.len(), ranks.len());
+            assert!(targets.is_sorted(), "queries of a sorted batch must be sorted");
+
+            fn aux<B, T,>(data: &[B], targets: &[T], ranks: &mut [usize], offset: usize,)
+            where
+                B: Borrow<T,>,
+                T: PartialOrd,
+            {
+                if targets.is_empty() {
+                    return;
+                }
+
+                let mid = targets.len() / 2;
+                let rank = $base(data, &targets[mid],);
+                ranks[mid] = offset + rank;
+
+                let (left, right,) = ranks.split_at_mut(mid,);
+                aux(&data[..rank], &targets[..mid], left, offset,);
+                aux(&data[rank..], &targets[mid + 1..], &mut right[1..], offset + rank,);
+            }
+
+            aux(data, targets, ranks, 0,);
+        }
+    };
+}
+
+impl_bound_sorted_batch!(lower_bound_sorted_batch, lower_bound);
+impl_bound_sorted_batch!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_sorted_batch,
+    upper_bound
+);
+
+// Runs a sorted batch search over queries in any order: they are sorted first, and
+// their ranks scattered back to the original order. Sorted queries go through as is.
+pub fn with_sorted_queries<T, F,>(targets: &[T], ranks: &mut [usize], search: F,)
+where
+    T: PartialOrd + Clone,
+    F: FnOnce(&[T], &mut [usize],),
+{
+    assert_eq!(targets.len(), ranks.len());
+
+    if targets.is_sorted() {
+        search(targets, ranks,);
+        return;
+    }
+
+    let mut order: Vec<usize,> = (0..targets.len()).collect();
+    order.sort_unstable_by(|&a, &b| {
+        targets[a].partial_cmp(&targets[b],).unwrap_or(std::cmp::Ordering::Equal,)
+    },);
+
+    let sorted: Vec<T,> = order.iter().map(|&i| targets[i].clone(),).collect();
+    let mut sorted_ranks = vec![0; targets.len()];
+    search(&sorted, &mut sorted_ranks,);
+
+    for (&i, &rank,) in order.iter().zip(&sorted_ranks,) {
+        ranks[i] = rank;
+    }
+}
+
+// Probe position for target, assuming data[lo] < target <= data[hi - 1]: keys are
+// expected to be evenly spread between both ends.
+#[inline(always)]
+fn interpolate<K: Numeric,>(data: &[K], lo: usize, hi: usize, target: K,) -> usize {
+    let (first, last,) = (data[lo].to_f64(), data[hi - 1].to_f64(),);
+    let fraction = (target.to_f64() - first) / (last - first);
+
+    lo + ((fraction * (hi - 1 - lo) as f64) as usize).min(hi - 1 - lo,)
+}
+
+#[must_use]
+#[inline(always)]
+pub fn lower_bound_interpolation<K: Numeric,>(data: &[K], target: &K,) -> usize {
+    let t = *target;
+    let (mut lo, mut hi,) = (0, data.len(),);
+
+    while lo < hi {
+        if data[lo] >= t {
+            return lo;
+        }
+        if data[hi - 1] < t {
+            return hi;
+        }
+
+        let mid = interpolate(data, lo, hi, t,);
+
+        if data[mid] >= t {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    lo
+}
+
+#[must_use]
+#[inline(always)]
+pub fn lower_bound_interpolation_sequential<K: Numeric,>(
+    data: &[K],
+    target: &K,
+) -> usize {
+    let t = *target;
+    let n = data.len();
+
+    if n == 0 || data[0] >= t {
+        return 0;
+    }
+    if data[n - 1] < t {
+        return n;
+    }
+
+    // A single probe, then a linear scan towards the answer
+    let mut i = interpolate(data, 0, n, t,);
+
+    if data[i] >= t {
+        while data[i - 1] >= t {
+            i -= 1;
+        }
+    } else {
+        while data[i] < t {
+            i += 1;
+        }
+    }
+
+    i
+}
+
+// Distance of the guard probes around an interpolation probe
+const GUARD: usize = 16;
+
+// Interpolation steps that fail to halve the range before giving up on them
+const MAX_BAD_STEPS: usize = 2;
+
+#[must_use]
+#[inline(always)]
+pub fn lower_bound_interpolation_guarded<K: Numeric,>(data: &[K], target: &K,) -> usize {
+    let t = *target;
+    let (mut lo, mut hi,) = (0, data.len(),);
+    let mut bad_steps = 0;
+
+    while hi - lo > GUARD && bad_steps < MAX_BAD_STEPS {
+        if data[lo] >= t {
+            return lo;
+        }
+        if data[hi - 1] < t {
+            return hi;
+        }
+
+        let size = hi - lo;
+        let mid = interpolate(data, lo, hi, t,);
+
+        // A probe close to the answer lands on the wrong side of it half of the time,
+        // the guard probe closes the range from the other side.
+        if data[mid] >= t {
+            hi = mid;
+            if mid - lo > GUARD && data[mid - GUARD] < t {
+                lo = mid - GUARD + 1;
+            }
+        } else {
+            lo = mid + 1;
+            if hi - lo > GUARD && data[lo + GUARD] >= t {
+                hi = lo + GUARD;
+            }
+        }
+
+        if hi - lo > size / 2 {
+            bad_steps += 1;
+        }
+    }
+
+    lo + lower_bound(&data[lo..hi], &t,)
+}
+
+
 #[cfg(test)]
 mod tests {
+    use rand::RngExt;
+
     use super::*;
 
     #[test]
@@ -103,4 +424,235 @@ mod tests {
         assert_eq!(lower_bound(&empty, &10), 0);
         assert_eq!(upper_bound(&empty, &10), 0);
     }
+
+    #[test]
+    fn test_interpolation_empty_array() {
+        let empty: Vec<i32,> = vec![];
+        assert_eq!(lower_bound_interpolation(&empty, &10), 0);
+        assert_eq!(lower_bound_interpolation_sequential(&empty, &10), 0);
+        assert_eq!(lower_bound_interpolation_guarded(&empty, &10), 0);
+    }
+
+    #[test]
+    fn test_equal_range_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+
+        assert_eq!(equal_range(&data, &20), (1, 4));
+        assert_eq!(equal_range(&data, &25), (4, 4));
+        assert_eq!(equal_range(&data, &5), (0, 0));
+        assert_eq!(equal_range(&data, &50), (6, 6));
+
+        assert_eq!(count(&data, &20), 3);
+        assert_eq!(count(&data, &40), 1);
+        assert_eq!(count(&data, &25), 0);
+
+        assert_eq!(count_in_range(&data, &20, &40), 4);
+        assert_eq!(count_in_range(&data, &0, &100), 6);
+        assert_eq!(count_in_range(&data, &40, &20), 0);
+    }
+
+    #[test]
+    fn test_count_fuzzy() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> = (0..2000).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+
+        for target in -2..102 {
+            let expected = data.iter().filter(|&&x| x == target,).count();
+            assert_eq!(count(&data, &target), expected);
+            assert_eq!(
+                equal_range(&data, &target),
+                (
+                    data.partition_point(|&x| x < target,),
+                    data.partition_point(|&x| x <= target,)
+                )
+            );
+        }
+
+        for _ in 0..1000 {
+            let (lo, hi,) = (rng.random_range(-2..102,), rng.random_range(-2..102,),);
+            let expected = data.iter().filter(|&&x| lo <= x && x < hi,).count();
+            assert_eq!(count_in_range(&data, &lo, &hi), expected);
+        }
+    }
+
+    #[test]
+    fn test_exponential_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+
+        for hint in 0..=7 {
+            for direction in [Direction::Forward, Direction::Backward,] {
+                assert_eq!(lower_bound_exponential(&data, &20, hint, direction), 1);
+                assert_eq!(lower_bound_exponential(&data, &25, hint, direction), 4);
+                assert_eq!(lower_bound_exponential(&data, &5, hint, direction), 0);
+                assert_eq!(lower_bound_exponential(&data, &50, hint, direction), 6);
+
+                assert_eq!(upper_bound_exponential(&data, &20, hint, direction), 4);
+                assert_eq!(upper_bound_exponential(&data, &30, hint, direction), 5);
+                assert_eq!(upper_bound_exponential(&data, &5, hint, direction), 0);
+                assert_eq!(upper_bound_exponential(&data, &50, hint, direction), 6);
+            }
+        }
+
+        let empty: Vec<i32,> = vec![];
+        assert_eq!(lower_bound_exponential(&empty, &10, 0, Direction::Forward), 0);
+        assert_eq!(upper_bound_exponential(&empty, &10, 3, Direction::Backward), 0);
+    }
+
+    #[test]
+    fn test_exponential_fuzzy() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> = (0..3000).map(|_| rng.random_range(0..500,),).collect();
+        data.sort_unstable();
+
+        for _ in 0..5000 {
+            let target = rng.random_range(-5..505,);
+            let hint = rng.random_range(0..3010,);
+            let direction = if rng.random::<bool>() {
+                Direction::Forward
+            } else {
+                Direction::Backward
+            };
+
+            assert_eq!(
+                lower_bound_exponential(&data, &target, hint, direction),
+                lower_bound(&data, &target),
+                "Lower bound mismatch for target {}, hint {}, {:?}",
+                target,
+                hint,
+                direction
+            );
+            assert_eq!(
+                upper_bound_exponential(&data, &target, hint, direction),
+                upper_bound(&data, &target),
+                "Upper bound mismatch for target {}, hint {}, {:?}",
+                target,
+                hint,
+                direction
+            );
+        }
+    }
+
+    #[test]
+    fn test_sorted_batch_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, 10, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..100,),).collect();
+            data.sort_unstable();
+
+            let targets: Vec<i32,> =
+                (0..500).map(|_| rng.random_range(-5..105,),).collect();
+            let mut ranks = vec![0; targets.len()];
+
+            // Unsorted queries, sorted and scattered back
+            with_sorted_queries(&targets, &mut ranks, |t, r| {
+                lower_bound_sorted_batch(&data, t, r,)
+            },);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(rank, lower_bound(&data, target,), "n = {}", n);
+            }
+
+            with_sorted_queries(&targets, &mut ranks, |t, r| {
+                upper_bound_sorted_batch(&data, t, r,)
+            },);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(rank, upper_bound(&data, target,), "n = {}", n);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "must be sorted")]
+    fn test_sorted_batch_unsorted_queries() {
+        let data = vec![10, 20, 30];
+        let mut ranks = vec![0; 2];
+        lower_bound_sorted_batch(&data, &[20, 10,], &mut ranks,);
+    }
+
+    #[test]
+    fn test_interpolation_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+
+        for search in [
+            lower_bound_interpolation::<i32,>,
+            lower_bound_interpolation_sequential::<i32,>,
+            lower_bound_interpolation_guarded::<i32,>,
+        ] {
+            assert_eq!(search(&data, &20), 1);
+            assert_eq!(search(&data, &30), 4);
+            assert_eq!(search(&data, &25), 4);
+            assert_eq!(search(&data, &5), 0);
+            assert_eq!(search(&data, &50), 6);
+        }
+    }
+
+    fn check_interpolation<K: Numeric + std::fmt::Debug,>(data: &[K], targets: &[K],) {
+        for target in targets {
+            let expected = lower_bound(data, target,);
+
+            assert_eq!(
+                lower_bound_interpolation(data, target,),
+                expected,
+                "{:?}",
+                target
+            );
+            assert_eq!(
+                lower_bound_interpolation_sequential(data, target,),
+                expected,
+                "{:?}",
+                target
+            );
+            assert_eq!(
+                lower_bound_interpolation_guarded(data, target,),
+                expected,
+                "{:?}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_interpolation_fuzzy() {
+        let mut rng = rand::rng();
+
+        // Uniform keys
+        let mut data: Vec<i32,> =
+            (0..10_000).map(|_| rng.random_range(0..i32::MAX,),).collect();
+        data.sort_unstable();
+        let targets: Vec<i32,> =
+            (0..2000).map(|_| rng.random_range(0..i32::MAX,),).collect();
+        check_interpolation(&data, &targets,);
+
+        // Heavy duplicates
+        let mut data: Vec<i32,> =
+            (0..10_000).map(|_| rng.random_range(0..20,),).collect();
+        data.sort_unstable();
+        let targets: Vec<i32,> = (-2..22).collect();
+        check_interpolation(&data, &targets,);
+
+        // Adversarial: exponentially spread keys
+        let mut data: Vec<u64,> =
+            (0..10_000).map(|_| 1u64 << rng.random_range(0..64u32,),).collect();
+        data.extend([0, u64::MAX,],);
+        data.sort_unstable();
+        let targets: Vec<u64,> = data
+            .iter()
+            .step_by(7,)
+            .copied()
+            .chain((0..2000).map(|_| rng.random(),),)
+            .collect();
+        check_interpolation(&data, &targets,);
+
+        // Floats
+        let mut data: Vec<f64,> =
+            (0..10_000).map(|_| rng.random_range(-1e9..1e9,),).collect();
+        data.sort_unstable_by(|a, b| a.total_cmp(b,),);
+        let targets: Vec<f64,> =
+            (0..2000).map(|_| rng.random_range(-1.1e9..1.1e9,),).collect();
+        check_interpolation(&data, &targets,);
+    }
 }
@@ -1,26 +1,66 @@
-#[cfg(target_arch = "aarch64")]
-use std::arch::asm;
-#[cfg(target_arch = "x86_64")]
-use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+#[cfg(simd = "neon")]
+use std::arch::{
+    aarch64::{
+        vbslq_u32,
+        vcgtq_s32,
+        vcleq_u32,
+        vdupq_n_u32,
+        vld1q_s32,
+        vmaxvq_u32,
+        vmvnq_u32,
+        vorrq_u32,
+        vshlq_n_u32,
+        vst1q_u32,
+        vsubq_u32,
+    },
+    asm,
+};
+#[cfg(simd = "x86")]
+use std::arch::x86_64::{
+    __m256i,
+    _mm256_add_epi32,
+    _mm256_blendv_epi8,
+    _mm256_cmpgt_epi32,
+    _mm256_loadu_si256,
+    _mm256_mask_i32gather_epi32,
+    _mm256_movemask_epi8,
+    _mm256_set1_epi32,
+    _mm256_setzero_si256,
+    _mm256_slli_epi32,
+    _mm256_storeu_si256,
+    _mm256_sub_epi32,
+    _mm256_xor_si256,
+    _mm_prefetch,
+    _MM_HINT_T0,
+};
 use std::borrow::Borrow;
 
+use crate::backend::Backend;
+#[cfg(simd = "x86")]
+use crate::backend::with_avx2;
 
-#[cfg(target_arch = "x86_64")]
+
+#[cfg(simd = "x86")]
 const PREFETCH_OFFSET: usize = 1 + 8;
-#[cfg(target_arch = "aarch64")]
+#[cfg(simd = "neon")]
+const PREFETCH_OFFSET: usize = 1;
+#[cfg(simd = "none")]
 const PREFETCH_OFFSET: usize = 1;
 
 
 #[inline(always)]
 unsafe fn prefetch(ptr: *const i8,) {
-    #[cfg(target_arch = "x86_64")]
+    #[cfg(simd = "x86")]
     unsafe {
         _mm_prefetch(ptr, _MM_HINT_T0,);
     }
-    #[cfg(target_arch = "aarch64")]
+    #[cfg(simd = "neon")]
     unsafe {
         asm!("prfm pldl1keep, [{}]", in(reg) ptr);
     }
+    // No portable prefetch, the hardware prefetchers are left alone
+    #[cfg(simd = "none")]
+    let _ = ptr;
 }
 
 
@@ -47,8 +87,149 @@ pub fn create_layout_eytzinger<T: Clone,>(data: &[T],) -> Vec<T,> {
     eytzinger_data
 }
 
+// Same layout without recursion: every slot reads the key of its sorted rank
+#[must_use]
+pub fn create_layout_eytzinger_iterative<T: Clone,>(data: &[T],) -> Vec<T,> {
+    let n = data.len();
+    (0..n).map(|idx| data[index_to_rank(n, idx,)].clone(),).collect()
+}
+
+// Same layout, one level at a time: the perfect ranks of a level are evenly spaced, so
+// each level is a strided pass over the sorted data, written out sequentially.
+#[must_use]
+pub fn create_layout_eytzinger_blocked<T: Clone,>(data: &[T],) -> Vec<T,> {
+    let n = data.len();
+    let mut eytzinger_data = Vec::with_capacity(n,);
+    if n == 0 {
+        return eytzinger_data;
+    }
+
+    let height = (usize::BITS - n.leading_zeros()) as usize;
+    let leaves = n + 1 - (1 << (height - 1));
+
+    for depth in 0..height {
+        let first = (1 << depth) - 1;
+        let last = std::cmp::min(2 * first + 1, n,);
+
+        let stride = 1 << (height - depth);
+        let mut perfect: usize = (1 << (height - 1 - depth)) - 1;
+
+        for _ in first..last {
+            let rank = perfect - (perfect + 1).saturating_sub(2 * leaves,) / 2;
+            eytzinger_data.push(data[rank].clone(),);
+            perfect += stride;
+        }
+    }
+
+    eytzinger_data
+}
+
+// Same layout as the iterative builder, the slots being split into contiguous chunks
+// filled by threads threads.
+#[must_use]
+pub fn create_layout_eytzinger_parallel<T: Clone + Send + Sync,>(
+    data: &[T],
+    threads: usize,
+) -> Vec<T,> {
+    let n = data.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut eytzinger_data = vec![data[0].clone(); n];
+    let chunk = n.div_ceil(threads.max(1,),);
+
+    std::thread::scope(|scope| {
+        for (c, slots,) in eytzinger_data.chunks_mut(chunk,).enumerate() {
+            scope.spawn(move || {
+                for (offset, slot,) in slots.iter_mut().enumerate() {
+                    *slot = data[index_to_rank(n, c * chunk + offset,)].clone();
+                }
+            },);
+        }
+    },);
+
+    eytzinger_data
+}
+
+// Splits data into groups of group elements and moves the last element of each group
+// to the front, keeping the order of both parts. Each half is split recursively, then
+// a rotation swaps the rest of the left groups with the front of the right groups:
+// O(n log n) moves and no buffer beyond the O(log n) recursion.
+pub fn unshuffle<T,>(data: &mut [T], group: usize,) {
+    let groups = data.len() / group;
+    if groups <= 1 {
+        data.rotate_right(groups,);
+        return;
+    }
+
+    let half = groups / 2;
+    let (left, right,) = data.split_at_mut(half * group,);
+    unshuffle(left, group,);
+    unshuffle(right, group,);
+
+    data[half..half * group + groups - half].rotate_left(half * (group - 1),);
+}
+
+// Same layout built in place, bottom level first. In sorted order the leaves are the
+// even positions up to the last leaf, interleaved with the first internal nodes, so
+// moving the internal nodes to the front and the leaves to the back leaves the sorted
+// keys of the perfect tree above, to be laid out the same way.
+pub fn create_layout_eytzinger_in_place<T,>(data: &mut [T],) {
+    let mut n = data.len();
+
+    while n > 1 {
+        let height = usize::BITS - n.leading_zeros();
+        let leaves = n + 1 - (1 << (height - 1));
+        let internal = n - leaves;
+
+        // A perfect tree ends with a leaf which is already at its place
+        let pairs = std::cmp::min(leaves, internal,);
+        unshuffle(&mut data[..2 * pairs], 2,);
+        data[pairs..n].rotate_left(leaves,);
+
+        n = internal;
+    }
+}
+
+
+// An Eytzinger layout of n elements is a perfect tree whose last level is partially
+// filled from the left. The sorted rank of a node is its in-order position in that
+// perfect tree, minus the missing leaves which would have come before it.
+#[must_use]
+#[inline(always)]
+pub fn index_to_rank(n: usize, idx: usize,) -> usize {
+    debug_assert!(idx < n);
+
+    let height = usize::BITS - n.leading_zeros();
+    let leaves = n + 1 - (1 << (height - 1));
+
+    let i = idx + 1;
+    let depth = usize::BITS - 1 - i.leading_zeros();
+    let perfect = ((2 * (i - (1 << depth)) + 1) << (height - 1 - depth)) - 1;
+
+    perfect - (perfect + 1).saturating_sub(2 * leaves,) / 2
+}
+
+#[must_use]
+#[inline(always)]
+pub fn rank_to_index(n: usize, rank: usize,) -> usize {
+    debug_assert!(rank < n);
+
+    let height = usize::BITS - n.leading_zeros();
+    let leaves = n + 1 - (1 << (height - 1));
+
+    // Past the last present leaf, only internal nodes (odd perfect ranks) remain.
+    let perfect = if rank < 2 * leaves { rank } else { 2 * (rank - leaves) + 1 };
+
+    let shift = (perfect + 1).trailing_zeros();
+    let depth = height - 1 - shift;
+
+    (1 << depth) + ((perfect + 1) >> (shift + 1)) - 1
+}
 
-#[expect(unused)]
+
+#[cfg_attr(not(test), expect(unused))]
 #[must_use]
 #[inline(always)]
 pub fn lower_bound<B, T,>(data: &[B], target: &T,) -> Option<usize,>
@@ -65,7 +246,7 @@ where
     let mut cur = 0;
 
     while cur < n {
-        if data[cur as usize].borrow() >= target {
+        if data[cur].borrow() >= target {
             res = Some(cur,);
             cur = 2 * cur + 1;
         } else {
@@ -76,7 +257,7 @@ where
     res
 }
 
-#[expect(unused)]
+#[cfg_attr(not(test), expect(unused))]
 #[must_use]
 #[inline(always)]
 pub fn upper_bound<B, T,>(data: &[B], target: &T,) -> Option<usize,>
@@ -93,7 +274,7 @@ where
     let mut cur = 0;
 
     while cur < n {
-        if data[cur as usize].borrow() > target {
+        if data[cur].borrow() > target {
             res = Some(cur,);
             cur = 2 * cur + 1;
         } else {
@@ -105,7 +286,7 @@ where
 }
 
 
-#[expect(unused)]
+#[cfg_attr(not(test), expect(unused))]
 #[must_use]
 #[inline(always)]
 pub fn lower_bound_prefetched<B, T,>(data: &[B], target: &T,) -> Option<usize,>
@@ -131,7 +312,7 @@ where
             }
         }
 
-        if data[cur as usize].borrow() >= target {
+        if data[cur].borrow() >= target {
             res = Some(cur,);
             cur = 2 * cur + 1;
         } else {
@@ -142,7 +323,7 @@ where
     res
 }
 
-#[expect(unused)]
+#[cfg_attr(not(test), expect(unused))]
 #[must_use]
 #[inline(always)]
 pub fn upper_bound_prefetched<B, T,>(data: &[B], target: &T,) -> Option<usize,>
@@ -168,7 +349,7 @@ where
             }
         }
 
-        if data[cur as usize].borrow() > target {
+        if data[cur].borrow() > target {
             res = Some(cur,);
             cur = 2 * cur + 1;
         } else {
@@ -217,7 +398,6 @@ where
     res
 }
 
-#[expect(unused)]
 #[must_use]
 #[inline(always)]
 pub fn upper_bound_prefetched_branchless<B, T,>(data: &[B], target: &T,) -> Option<usize,>
@@ -256,8 +436,687 @@ where
 }
 
 
+#[must_use]
+#[inline(always)]
+pub fn lower_bound_branchless<B, T,>(data: &[B], target: &T,) -> Option<usize,>
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let n = data.len();
+    if n == 0 {
+        return None;
+    }
+
+    let mut res: Option<usize,> = None;
+    let mut cur = 0;
+
+    while cur < n {
+        let right = (data[cur].borrow() < target) as usize;
+
+        if right == 0 {
+            res = Some(cur,);
+        }
+
+        cur = 2 * cur + 1 + right
+    }
+
+    res
+}
+
+#[must_use]
+#[inline(always)]
+pub fn upper_bound_branchless<B, T,>(data: &[B], target: &T,) -> Option<usize,>
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let n = data.len();
+    if n == 0 {
+        return None;
+    }
+
+    let mut res: Option<usize,> = None;
+    let mut cur = 0;
+
+    while cur < n {
+        let right = (data[cur].borrow() <= target) as usize;
+
+        if right == 0 {
+            res = Some(cur,);
+        }
+
+        cur = 2 * cur + 1 + right
+    }
+
+    res
+}
+
+
+// Same descents as a membership test, which is over as soon as they meet the key.
+#[must_use]
+#[inline(always)]
+pub fn contains_prefetched_branchless<B, T,>(data: &[B], target: &T,) -> bool
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let base_ptr = data.as_ptr();
+    let n = data.len();
+
+    let mut cur = 0;
+
+    while cur < n {
+        unsafe {
+            let lookahead_idx = (2 * cur + PREFETCH_OFFSET) * 4;
+            if lookahead_idx < n {
+                let ptr = base_ptr.add(lookahead_idx,) as *const i8;
+                prefetch(ptr,);
+            }
+        }
+
+        // The next node is computed before the exit is taken, so that its address does
+        // not wait on the equality branch
+        let key = data[cur].borrow();
+        let found = key == target;
+        cur = 2 * cur + 1 + (key < target) as usize;
+
+        if found {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[must_use]
+#[inline(always)]
+pub fn contains_branchless<B, T,>(data: &[B], target: &T,) -> bool
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let n = data.len();
+
+    let mut cur = 0;
+
+    while cur < n {
+        let key = data[cur].borrow();
+        let found = key == target;
+        cur = 2 * cur + 1 + (key < target) as usize;
+
+        if found {
+            return true;
+        }
+    }
+
+    false
+}
+
+
+// Prefetch strategy of the searches of an index, chosen from its backend once it is
+// built: the portable backend has no prefetch instruction and gains nothing from the
+// address computations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prefetch {
+    // Branchless descent alone
+    Disabled,
+    // Branchless descent prefetching the descendants PREFETCH_OFFSET levels down
+    Lookahead,
+}
+
+impl Prefetch {
+    #[must_use]
+    pub fn of(backend: Backend,) -> Self {
+        match backend {
+            | Backend::Scalar => Self::Disabled,
+            | _ => Self::Lookahead,
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn lower_bound<B, T,>(self, data: &[B], target: &T,) -> Option<usize,>
+    where
+        B: Borrow<T,>,
+        T: PartialOrd + ?Sized,
+    {
+        match self {
+            | Self::Disabled => lower_bound_branchless(data, target,),
+            | Self::Lookahead => lower_bound_prefetched_branchless(data, target,),
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn upper_bound<B, T,>(self, data: &[B], target: &T,) -> Option<usize,>
+    where
+        B: Borrow<T,>,
+        T: PartialOrd + ?Sized,
+    {
+        match self {
+            | Self::Disabled => upper_bound_branchless(data, target,),
+            | Self::Lookahead => upper_bound_prefetched_branchless(data, target,),
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn contains<B, T,>(self, data: &[B], target: &T,) -> bool
+    where
+        B: Borrow<T,>,
+        T: PartialOrd + ?Sized,
+    {
+        match self {
+            | Self::Disabled => contains_branchless(data, target,),
+            | Self::Lookahead => contains_prefetched_branchless(data, target,),
+        }
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn lower_bound_rank<B, T,>(self, data: &[B], target: &T,) -> usize
+    where
+        B: Borrow<T,>,
+        T: PartialOrd + ?Sized,
+    {
+        let n = data.len();
+        self.lower_bound(data, target,).map_or(n, |i| index_to_rank(n, i,),)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn upper_bound_rank<B, T,>(self, data: &[B], target: &T,) -> usize
+    where
+        B: Borrow<T,>,
+        T: PartialOrd + ?Sized,
+    {
+        let n = data.len();
+        self.upper_bound(data, target,).map_or(n, |i| index_to_rank(n, i,),)
+    }
+}
+
+
+#[must_use]
+#[inline(always)]
+pub fn lower_bound_rank<B, T,>(data: &[B], target: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let n = data.len();
+    lower_bound_prefetched_branchless(data, target,).map_or(n, |i| index_to_rank(n, i,),)
+}
+
+#[must_use]
+#[inline(always)]
+pub fn upper_bound_rank<B, T,>(data: &[B], target: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let n = data.len();
+    upper_bound_prefetched_branchless(data, target,).map_or(n, |i| index_to_rank(n, i,),)
+}
+
+
+#[must_use]
+#[inline(always)]
+pub fn equal_range<B, T,>(data: &[B], target: &T,) -> (usize, usize,)
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    (lower_bound_rank(data, target,), upper_bound_rank(data, target,),)
+}
+
+#[must_use]
+#[inline(always)]
+pub fn count<B, T,>(data: &[B], target: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    let (lower, upper,) = equal_range(data, target,);
+    upper - lower
+}
+
+// Number of elements in the half-open range [lo, hi)
+#[must_use]
+#[inline(always)]
+pub fn count_in_range<B, T,>(data: &[B], lo: &T, hi: &T,) -> usize
+where
+    B: Borrow<T,>,
+    T: PartialOrd + ?Sized,
+{
+    lower_bound_rank(data, hi,).saturating_sub(lower_bound_rank(data, lo,),)
+}
+
+
+const MAX_DEPTH: usize = usize::BITS as usize;
+
+// Search path left by the previous finger search: the node visited at each depth, and
+// the in-order neighbours of its subtree, which are the nearest ancestors where the
+// path went right (before) and left (after).
+pub struct Finger {
+    depth:  usize,
+    nodes:  [usize; MAX_DEPTH],
+    before: [Option<usize,>; MAX_DEPTH],
+    after:  [Option<usize,>; MAX_DEPTH],
+}
+
+impl Finger {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            depth:  0,
+            nodes:  [0; MAX_DEPTH],
+            before: [None; MAX_DEPTH],
+            after:  [None; MAX_DEPTH],
+        }
+    }
+}
+
+impl Default for Finger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// The search restarts from the deepest node of the previous path whose subtree is
+// known to hold the answer: its neighbour before goes right, its neighbour after goes
+// left. For increasing and close queries, this is only a few levels up.
+macro_rules! impl_bound_finger {
+    ($(#[$attr:meta])* $name:ident, $go_left:ident) => {
+        $(#[$attr])*
+        #[must_use]
+        #[inline(always)]
+        pub fn $name<B, T,>(data: &[B], target: &T, finger: &mut Finger,) -> Option<usize,>
+        where
+            B: Borrow<T,>,
+            T: PartialOrd + ?Sized,
+        {
+            let n = data.len();
+
+            let mut depth = finger.depth.saturating_sub(1,);
+            while depth > 0
+                && (finger.before[depth].is_some_and(|i| data[i].borrow().$go_left(target,),)
+                    || finger.after[depth].is_some_and(|i| !data[i].borrow().$go_left(target,),))
+            {
+                depth -= 1;
+            }
+
+            let (mut cur, mut before, mut after,) = if finger.depth == 0 {
+                (0, None, None,)
+            } else {
+                (finger.nodes[depth], finger.before[depth], finger.after[depth],)
+            };
+
+            while cur < n {
+                finger.nodes[depth] = cur;
+                finger.before[depth] = before;
+                finger.after[depth] = after;
+
+                if data[cur].borrow().$go_left(target,) {
+                    after = Some(cur,);
+                    cur = 2 * cur + 1;
+                } else {
+                    before = Some(cur,);
+                    cur = 2 * cur + 2;
+                }
+
+                depth += 1;
+            }
+
+            finger.depth = depth;
+            after
+        }
+    };
+}
+
+impl_bound_finger!(lower_bound_finger, ge);
+impl_bound_finger!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_finger,
+    gt
+);
+
+
+// Searches of a batch move down the tree in lock-step, one level at a time. The next
+// node of each search is prefetched as soon as it is known, and only read once every
+// other search of the group has done its own step, so the cache misses overlap. The
+// _with variant only prefetches when the point searches of the given backend do.
+macro_rules! impl_bound_batch {
+    ($(#[$attr:meta])* $name:ident, $name_with:ident, $go_right:ident) => {
+        $(#[$attr])*
+        #[inline(always)]
+        pub fn $name<B, T, const G: usize,>(
+            data: &[B],
+            targets: &[T],
+            results: &mut [Option<usize,>],
+        ) where
+            B: Borrow<T,>,
+            T: PartialOrd,
+        {
+            $name_with::<B, T, G,>(Backend::current(), data, targets, results,);
+        }
+
+        #[inline(always)]
+        pub fn $name_with<B, T, const G: usize,>(
+            backend: Backend,
+            data: &[B],
+            targets: &[T],
+            results: &mut [Option<usize,>],
+        ) where
+            B: Borrow<T,>,
+            T: PartialOrd,
+        {
+            const { assert!(G > 0) };
+            assert_eq!(targets.len(), results.len());
+
+            let base_ptr = data.as_ptr();
+            let n = data.len();
+            let lookahead = Prefetch::of(backend,) == Prefetch::Lookahead;
+
+            for (targets, results,) in targets.chunks(G,).zip(results.chunks_mut(G,),) {
+                let mut cur = [0; G];
+                results.fill(None,);
+
+                let mut active = n > 0;
+                while active {
+                    active = false;
+
+                    for (q, target,) in targets.iter().enumerate() {
+                        let c = cur[q];
+                        if c >= n {
+                            continue;
+                        }
+
+                        let right = data[c].borrow().$go_right(target,) as usize;
+
+                        if right == 0 {
+                            results[q] = Some(c,);
+                        }
+
+                        let next = 2 * c + 1 + right;
+                        if next < n {
+                            if lookahead {
+                                unsafe { prefetch(base_ptr.add(next,) as *const i8,) };
+                            }
+                            active = true;
+                        }
+                        cur[q] = next;
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_bound_batch!(lower_bound_batch, lower_bound_batch_with, lt);
+impl_bound_batch!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_batch,
+    upper_bound_batch_with,
+    le
+);
+
+
+// Eight searches at once, one per lane. Nodes are numbered from 1 so that the children
+// of k are 2k and 2k + 1: every level gathers the node of each lane still inside the
+// tree, and moves it to k = 2k + right. Returns the node where each lane left the tree.
+#[inline(always)]
+unsafe fn vertical_kernel<const UPPER: bool,>(
+    backend: Backend,
+    base_ptr: *const i32,
+    n: usize,
+    targets: *const i32,
+) -> [u32; 8] {
+    match backend {
+        #[cfg(simd = "x86")]
+        | Backend::Avx512 | Backend::Avx2 => unsafe {
+            with_avx2(
+                #[inline(always)]
+                || vertical_avx2::<UPPER,>(base_ptr, n, targets,),
+            )
+        },
+        #[cfg(simd = "neon")]
+        | Backend::Neon => unsafe { vertical_neon::<UPPER,>(base_ptr, n, targets,) },
+        | _ => unsafe { vertical_scalar::<UPPER,>(base_ptr, n, targets,) },
+    }
+}
+
+#[cfg(simd = "x86")]
+#[inline(always)]
+unsafe fn vertical_avx2<const UPPER: bool,>(
+    base_ptr: *const i32,
+    n: usize,
+    targets: *const i32,
+) -> [u32; 8] {
+    unsafe {
+        let t = _mm256_loadu_si256(targets as *const __m256i,);
+        let ones = _mm256_set1_epi32(-1,);
+        let sign = _mm256_set1_epi32(i32::MIN,);
+
+        // Node indices are unsigned, compared with the sign bit flipped
+        let last = _mm256_set1_epi32((n as u32 ^ 0x8000_0000) as i32,);
+        let mut k = _mm256_set1_epi32(1,);
+
+        loop {
+            let outside = _mm256_cmpgt_epi32(_mm256_xor_si256(k, sign,), last,);
+            let active = _mm256_xor_si256(outside, ones,);
+            if _mm256_movemask_epi8(active,) == 0 {
+                break;
+            }
+
+            let v = _mm256_mask_i32gather_epi32::<4,>(
+                _mm256_setzero_si256(),
+                base_ptr,
+                _mm256_add_epi32(k, ones,),
+                active,
+            );
+
+            let right = if UPPER {
+                _mm256_xor_si256(_mm256_cmpgt_epi32(v, t,), ones,)
+            } else {
+                _mm256_cmpgt_epi32(t, v,)
+            };
+
+            // right is -1 for the lanes going right
+            let next = _mm256_sub_epi32(_mm256_slli_epi32::<1,>(k,), right,);
+            k = _mm256_blendv_epi8(k, next, active,);
+        }
+
+        let mut nodes = [0; 8];
+        _mm256_storeu_si256(nodes.as_mut_ptr() as *mut __m256i, k,);
+        nodes
+    }
+}
+
+#[cfg(simd = "neon")]
+#[inline(always)]
+unsafe fn vertical_neon<const UPPER: bool,>(
+    base_ptr: *const i32,
+    n: usize,
+    targets: *const i32,
+) -> [u32; 8] {
+    unsafe {
+        // No gathers: the lanes are loaded one by one, over two registers
+        let last = vdupq_n_u32(n as u32,);
+        let t = [vld1q_s32(targets,), vld1q_s32(targets.add(4,),),];
+        let mut k = [vdupq_n_u32(1,); 2];
+
+        loop {
+            let active = [vcleq_u32(k[0], last,), vcleq_u32(k[1], last,),];
+            if vmaxvq_u32(vorrq_u32(active[0], active[1],),) == 0 {
+                break;
+            }
+
+            for h in 0..2 {
+                let mut lanes = [0; 4];
+                vst1q_u32(lanes.as_mut_ptr(), k[h],);
+
+                let values = lanes.map(|k| {
+                    if k as usize <= n { *base_ptr.add(k as usize - 1,) } else { 0 }
+                },);
+                let v = vld1q_s32(values.as_ptr(),);
+
+                let right = if UPPER {
+                    vmvnq_u32(vcgtq_s32(v, t[h],),)
+                } else {
+                    vcgtq_s32(t[h], v,)
+                };
+
+                // right is all ones, -1, for the lanes going right
+                let next = vsubq_u32(vshlq_n_u32::<1,>(k[h],), right,);
+                k[h] = vbslq_u32(active[h], next, k[h],);
+            }
+        }
+
+        let mut nodes = [0; 8];
+        vst1q_u32(nodes.as_mut_ptr(), k[0],);
+        vst1q_u32(nodes.as_mut_ptr().add(4,), k[1],);
+        nodes
+    }
+}
+
+#[inline(always)]
+unsafe fn vertical_scalar<const UPPER: bool,>(
+    base_ptr: *const i32,
+    n: usize,
+    targets: *const i32,
+) -> [u32; 8] {
+    unsafe {
+        // The lanes one after the other, with the same moves
+        std::array::from_fn(|lane| {
+            let t = *targets.add(lane,);
+            let mut k = 1;
+
+            while k <= n {
+                let v = *base_ptr.add(k - 1,);
+                let right = if UPPER { v <= t } else { v < t };
+                k = 2 * k + right as usize;
+            }
+
+            k as u32
+        },)
+    }
+}
+
+// The last node where a search went left is the path without its trailing right
+// moves, and that left move.
+#[inline(always)]
+fn vertical_result(k: u32,) -> Option<usize,> {
+    let node = (k as u64 >> (k.trailing_ones() + 1)) as usize;
+    node.checked_sub(1,)
+}
+
+// The last queries, less than a register, go through the point search of the backend
+macro_rules! impl_bound_vertical {
+    ($(#[$attr:meta])* $name:ident, $name_with:ident, $upper:literal, $scalar:ident) => {
+        $(#[$attr])*
+        pub fn $name(data: &[i32], targets: &[i32], results: &mut [Option<usize,>],) {
+            $name_with(Backend::current(), data, targets, results,);
+        }
+
+        pub fn $name_with(
+            backend: Backend,
+            data: &[i32],
+            targets: &[i32],
+            results: &mut [Option<usize,>],
+        ) {
+            assert_eq!(targets.len(), results.len());
+            // Lanes hold node indices up to 2n + 1 on 32 bits
+            assert!(data.len() < 1 << 31);
+
+            let base_ptr = data.as_ptr();
+            let n = data.len();
+
+            let mut targets = targets.chunks_exact(8,);
+            let mut results = results.chunks_exact_mut(8,);
+
+            for (targets, results,) in (&mut targets).zip(&mut results,) {
+                let nodes = unsafe { vertical_kernel::<$upper,>(backend, base_ptr, n, targets.as_ptr(),) };
+
+                for (res, k,) in results.iter_mut().zip(nodes,) {
+                    *res = vertical_result(k,);
+                }
+            }
+
+            for (target, res,) in targets.remainder().iter().zip(results.into_remainder(),) {
+                *res = Prefetch::of(backend,).$scalar(data, target,);
+            }
+        }
+    };
+}
+
+impl_bound_vertical!(lower_bound_vertical, lower_bound_vertical_with, false, lower_bound);
+impl_bound_vertical!(
+    #[expect(unused)]
+    upper_bound_vertical,
+    upper_bound_vertical_with,
+    true,
+    upper_bound
+);
+
+
+// Sorted queries going left at a node are a prefix of those reaching it: each node
+// splits its queries in two with one binary search, and each query range goes down
+// its own side of the tree.
+macro_rules! impl_bound_sorted_batch {
+    ($(#[$attr:meta])* $name:ident, $go_left:ident) => {
+        $(#[$attr])*
+        pub fn $name<B, T,>(data: &[B], targets: &[T], ranks: &mut [usize],)
+        where
+            B: Borrow<T,>,
+            T: PartialOrd,
+        {
+            assert_eq!(targets.len(), ranks.len());
+            assert!(targets.is_sorted(), "queries of a sorted batch must be sorted");
+
+            fn aux<B, T,>(
+                data: &[B],
+                k: usize,
+                targets: &[T],
+                ranks: &mut [usize],
+                res: Option<usize,>,
+            ) where
+                B: Borrow<T,>,
+                T: PartialOrd,
+            {
+                if targets.is_empty() {
+                    return;
+                }
+
+                let n = data.len();
+                if k >= n {
+                    ranks.fill(res.map_or(n, |i| index_to_rank(n, i,),),);
+                    return;
+                }
+
+                let split = targets.partition_point(|t| data[k].borrow().$go_left(t,),);
+                let (left, right,) = ranks.split_at_mut(split,);
+
+                aux(data, 2 * k + 1, &targets[..split], left, Some(k,),);
+                aux(data, 2 * k + 2, &targets[split..], right, res,);
+            }
+
+            aux(data, 0, targets, ranks, None,);
+        }
+    };
+}
+
+impl_bound_sorted_batch!(lower_bound_sorted_batch, ge);
+impl_bound_sorted_batch!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_sorted_batch,
+    gt
+);
+
 #[cfg(test)]
 mod tests {
+    use rand::RngExt;
+
     use super::*;
 
     #[test]
@@ -296,6 +1155,26 @@ mod tests {
         assert_eq!(layout, vec![4, 2, 5, 1, 3]);
     }
 
+    #[test]
+    fn test_builders_identical() {
+        for n in 0..1100 {
+            let input: Vec<usize,> = (0..n).collect();
+            let layout = create_layout_eytzinger(&input,);
+
+            assert_eq!(create_layout_eytzinger_iterative(&input,), layout, "n = {}", n);
+            assert_eq!(create_layout_eytzinger_blocked(&input,), layout, "n = {}", n);
+
+            let mut in_place = input.clone();
+            create_layout_eytzinger_in_place(&mut in_place,);
+            assert_eq!(in_place, layout, "n = {}", n);
+
+            for threads in [1, 3, 8,] {
+                let parallel = create_layout_eytzinger_parallel(&input, threads,);
+                assert_eq!(parallel, layout, "n = {}, threads = {}", n, threads);
+            }
+        }
+    }
+
     #[test]
     fn test_lower_bound_correctness() {
         let data = vec![10, 20, 20, 20, 30, 40];
@@ -415,4 +1294,287 @@ mod tests {
         // 5. Target larger than all (insert at end)
         assert_eq!(upper_bound_prefetched_branchless(&data, &50), None);
     }
+
+    #[test]
+    fn test_rank_imperfect_tree() {
+        // Layout of [1, 2, 3, 4, 5] is [4, 2, 5, 1, 3]
+        let ranks: Vec<usize,> = (0..5).map(|i| index_to_rank(5, i,),).collect();
+        assert_eq!(ranks, vec![3, 1, 4, 0, 2]);
+
+        let indices: Vec<usize,> = (0..5).map(|r| rank_to_index(5, r,),).collect();
+        assert_eq!(indices, vec![3, 1, 4, 0, 2]);
+    }
+
+    #[test]
+    fn test_rank_round_trip() {
+        for n in 1..600 {
+            let input: Vec<usize,> = (0..n).collect();
+            let layout = create_layout_eytzinger(&input,);
+
+            // Every value is its own sorted rank
+            for (idx, &rank,) in layout.iter().enumerate() {
+                assert_eq!(index_to_rank(n, idx,), rank, "n = {}, idx = {}", n, idx);
+                assert_eq!(rank_to_index(n, rank,), idx, "n = {}, rank = {}", n, rank);
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_rank_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+        let layout = create_layout_eytzinger(&data,);
+
+        assert_eq!(lower_bound_rank(&layout, &20), 1);
+        assert_eq!(lower_bound_rank(&layout, &25), 4);
+        assert_eq!(lower_bound_rank(&layout, &5), 0);
+        assert_eq!(lower_bound_rank(&layout, &50), 6);
+
+        assert_eq!(upper_bound_rank(&layout, &20), 4);
+        assert_eq!(upper_bound_rank(&layout, &30), 5);
+        assert_eq!(upper_bound_rank(&layout, &40), 6);
+
+        let empty: Vec<i32,> = vec![];
+        assert_eq!(lower_bound_rank(&empty, &10), 0);
+        assert_eq!(upper_bound_rank(&empty, &10), 0);
+    }
+
+    #[test]
+    fn test_bound_rank_fuzzy() {
+        let mut rng = rand::rng();
+
+        let n = 2000;
+        let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+
+        let layout = create_layout_eytzinger(&data,);
+
+        for _ in 0..1000 {
+            let target = rng.random_range(0..110,);
+
+            assert_eq!(
+                lower_bound_rank(&layout, &target,),
+                data.partition_point(|&x| x < target,),
+                "Lower bound mismatch for target {}",
+                target
+            );
+            assert_eq!(
+                upper_bound_rank(&layout, &target,),
+                data.partition_point(|&x| x <= target,),
+                "Upper bound mismatch for target {}",
+                target
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefetch_strategies_fuzzy() {
+        let mut rng = rand::rng();
+
+        let n = 2000;
+        let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+
+        let layout = create_layout_eytzinger(&data,);
+
+        for prefetch in [Prefetch::Disabled, Prefetch::Lookahead,] {
+            for _ in 0..1000 {
+                let target = rng.random_range(0..110,);
+
+                assert_eq!(
+                    prefetch.lower_bound_rank(&layout, &target,),
+                    data.partition_point(|&x| x < target,),
+                    "Lower bound mismatch for target {}, {:?}",
+                    target,
+                    prefetch
+                );
+                assert_eq!(
+                    prefetch.upper_bound_rank(&layout, &target,),
+                    data.partition_point(|&x| x <= target,),
+                    "Upper bound mismatch for target {}, {:?}",
+                    target,
+                    prefetch
+                );
+                assert_eq!(
+                    prefetch.contains(&layout, &target,),
+                    data.binary_search(&target,).is_ok(),
+                    "Membership mismatch for target {}, {:?}",
+                    target,
+                    prefetch
+                );
+            }
+        }
+        assert_eq!(Prefetch::of(Backend::Scalar), Prefetch::Disabled);
+    }
+
+    #[test]
+    fn test_bound_finger_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [1, 2, 5, 100, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..1000,),).collect();
+            data.sort_unstable();
+            let layout = create_layout_eytzinger(&data,);
+
+            // Increasing queries, then queries in any order
+            let mut targets: Vec<i32,> =
+                (0..500).map(|_| rng.random_range(-5..1005,),).collect();
+            targets.sort_unstable();
+            targets.extend((0..500).map(|_| rng.random_range(-5..1005,),),);
+
+            let mut lower_finger = Finger::new();
+            let mut upper_finger = Finger::new();
+
+            for target in targets {
+                assert_eq!(
+                    lower_bound_finger(&layout, &target, &mut lower_finger,),
+                    lower_bound(&layout, &target,),
+                    "Lower bound mismatch for target {}, n = {}",
+                    target,
+                    n
+                );
+                assert_eq!(
+                    upper_bound_finger(&layout, &target, &mut upper_finger,),
+                    upper_bound(&layout, &target,),
+                    "Upper bound mismatch for target {}, n = {}",
+                    target,
+                    n
+                );
+            }
+        }
+    }
+
+    fn check_batch<const G: usize,>(data: &[i32], layout: &[i32], targets: &[i32],) {
+        let mut results = vec![None; targets.len()];
+
+        lower_bound_batch::<_, _, G,>(layout, targets, &mut results,);
+        for (target, res,) in targets.iter().zip(&results,) {
+            let idx = data.partition_point(|x| x < target,);
+            assert_eq!(res.map(|i| layout[i],), data.get(idx,).copied(), "G = {}", G);
+        }
+
+        upper_bound_batch::<_, _, G,>(layout, targets, &mut results,);
+        for (target, res,) in targets.iter().zip(&results,) {
+            let idx = data.partition_point(|x| x <= target,);
+            assert_eq!(res.map(|i| layout[i],), data.get(idx,).copied(), "G = {}", G);
+        }
+    }
+
+    #[test]
+    fn test_bound_batch_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, 5, 100, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..1000,),).collect();
+            data.sort_unstable();
+            let layout = create_layout_eytzinger(&data,);
+
+            // Batch sizes that do and do not divide the number of queries
+            let targets: Vec<i32,> =
+                (0..1000).map(|_| rng.random_range(-5..1005,),).collect();
+            check_batch::<1,>(&data, &layout, &targets,);
+            check_batch::<7,>(&data, &layout, &targets,);
+            check_batch::<16,>(&data, &layout, &targets,);
+            check_batch::<64,>(&data, &layout, &targets,);
+        }
+    }
+
+    #[test]
+    fn test_bound_vertical_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, 5, 8, 100, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..1000,),).collect();
+            data.sort_unstable();
+            let layout = create_layout_eytzinger(&data,);
+
+            // Not a multiple of 8, the last queries go through the scalar search
+            let mut targets: Vec<i32,> =
+                (0..1003).map(|_| rng.random_range(-5..1005,),).collect();
+            targets.extend([i32::MIN, i32::MAX,],);
+            let mut results = vec![None; targets.len()];
+
+            for backend in Backend::supported() {
+                lower_bound_vertical_with(backend, &layout, &targets, &mut results,);
+                for (target, res,) in targets.iter().zip(&results,) {
+                    assert_eq!(
+                        *res,
+                        lower_bound(&layout, target,),
+                        "Lower bound, n = {}, {:?}",
+                        n,
+                        backend
+                    );
+                }
+
+                upper_bound_vertical_with(backend, &layout, &targets, &mut results,);
+                for (target, res,) in targets.iter().zip(&results,) {
+                    assert_eq!(
+                        *res,
+                        upper_bound(&layout, target,),
+                        "Upper bound, n = {}, {:?}",
+                        n,
+                        backend
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_sorted_batch_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, 5, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..100,),).collect();
+            data.sort_unstable();
+            let layout = create_layout_eytzinger(&data,);
+
+            let mut targets: Vec<i32,> =
+                (0..500).map(|_| rng.random_range(-5..105,),).collect();
+            targets.sort_unstable();
+            let mut ranks = vec![0; targets.len()];
+
+            lower_bound_sorted_batch(&layout, &targets, &mut ranks,);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(rank, data.partition_point(|x| x < target,), "n = {}", n);
+            }
+
+            upper_bound_sorted_batch(&layout, &targets, &mut ranks,);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(rank, data.partition_point(|x| x <= target,), "n = {}", n);
+            }
+        }
+    }
+
+    #[test]
+    fn test_count_fuzzy() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> = (0..2000).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+        let layout = create_layout_eytzinger(&data,);
+
+        for target in -2..102 {
+            assert_eq!(
+                equal_range(&layout, &target,),
+                (
+                    data.partition_point(|&x| x < target,),
+                    data.partition_point(|&x| x <= target,),
+                )
+            );
+            assert_eq!(
+                count(&layout, &target,),
+                data.iter().filter(|&&x| x == target,).count()
+            );
+        }
+
+        for _ in 0..1000 {
+            let (lo, hi,) = (rng.random_range(-2..102,), rng.random_range(-2..102,),);
+            let expected = data.iter().filter(|&&x| lo <= x && x < hi,).count();
+            assert_eq!(count_in_range(&layout, &lo, &hi,), expected);
+        }
+    }
 }
@@ -0,0 +1,310 @@
+use crate::basics::{self, Numeric};
+
+
+// One level of piecewise linear models: segment s starts at keys[s], which is at
+// position starts[s] of the level below, and predicts the position of a key k as
+// starts[s] + slopes[s] * (k - keys[s]).
+struct Level<K,> {
+    keys:   Vec<K,>,
+    slopes: Vec<f64,>,
+    starts: Vec<usize,>,
+}
+
+pub struct LearnedIndex<K,> {
+    epsilon: usize,
+    n:       usize,
+    // Bottom level first, the last level has a single segment
+    levels:  Vec<Level<K,>,>,
+}
+
+
+// Shrinking cone: a segment keeps the range of slopes that predict all of its points
+// within epsilon, and a new segment starts as soon as that range becomes empty.
+fn fit<K: Numeric,>(
+    points: impl Iterator<Item = (K, usize,),>,
+    epsilon: usize,
+) -> Level<K,> {
+    let mut level: Level<K,> =
+        Level { keys: Vec::new(), slopes: Vec::new(), starts: Vec::new(), };
+
+    let epsilon = epsilon as f64;
+    let (mut lo, mut hi,) = (0.0, f64::INFINITY,);
+
+    let slope = |lo: f64, hi: f64| if hi.is_finite() { (lo + hi) / 2.0 } else { lo };
+
+    for (key, pos,) in points {
+        if let (Some(&first_key,), Some(&start,),) =
+            (level.keys.last(), level.starts.last(),)
+        {
+            let dx = key.to_f64() - first_key.to_f64();
+            let dy = pos as f64 - start as f64;
+
+            // Distinct keys can be equal once converted, they cannot share a segment
+            if dx > 0.0 {
+                let (min, max,) = ((dy - epsilon) / dx, (dy + epsilon) / dx,);
+
+                if min <= hi && max >= lo {
+                    lo = lo.max(min,);
+                    hi = hi.min(max,);
+                    continue;
+                }
+            }
+
+            *level.slopes.last_mut().unwrap() = slope(lo, hi,);
+        }
+
+        level.keys.push(key,);
+        level.slopes.push(0.0,);
+        level.starts.push(pos,);
+        (lo, hi,) = (0.0, f64::INFINITY,);
+    }
+
+    if let Some(last,) = level.slopes.last_mut() {
+        *last = slope(lo, hi,);
+    }
+
+    level
+}
+
+
+impl<K: Numeric,> LearnedIndex<K,> {
+    #[must_use]
+    pub fn new(data: &[K], epsilon: usize,) -> Self {
+        // The bottom level maps every distinct key to its first position
+        let points = data
+            .iter()
+            .enumerate()
+            .filter(|&(i, key,)| i == 0 || data[i - 1] != *key,)
+            .map(|(i, &key,)| (key, i,),);
+
+        let mut levels = Vec::new();
+        if !data.is_empty() {
+            levels.push(fit(points, epsilon,),);
+        }
+
+        while let Some(level,) = levels.last()
+            && level.keys.len() > 1
+        {
+            let points =
+                level.keys.iter().copied().enumerate().map(|(i, key,)| (key, i,),);
+            levels.push(fit(points, epsilon,),);
+        }
+
+        Self { epsilon, n: data.len(), levels, }
+    }
+
+    #[must_use]
+    pub fn segments(&self,) -> usize {
+        self.levels.iter().map(|level| level.keys.len(),).sum()
+    }
+
+    #[must_use]
+    pub fn size_bytes(&self,) -> usize {
+        let segment = size_of::<K,>() + size_of::<f64,>() + size_of::<usize,>();
+        self.segments() * segment
+    }
+
+    // Window of positions of the level below (or of the data) in which the answer for
+    // target lies, knowing that it belongs to segment s of the level.
+    #[inline(always)]
+    fn window(
+        &self,
+        level: &Level<K,>,
+        s: usize,
+        target: K,
+        len: usize,
+    ) -> (usize, usize,) {
+        let start = level.starts[s];
+        let end = level.starts.get(s + 1,).copied().unwrap_or(len,);
+
+        let pred =
+            start as f64 + level.slopes[s] * (target.to_f64() - level.keys[s].to_f64());
+        let pred = (pred.max(start as f64,) as usize).min(end,);
+
+        // One more on each side for the truncated prediction and float rounding
+        let lo = pred.saturating_sub(self.epsilon + 1,).max(start,);
+        let hi = (pred + self.epsilon + 2).min(end,);
+
+        (lo, hi,)
+    }
+
+    // Segment of the bottom level whose keys range contains target
+    #[inline(always)]
+    fn segment(&self, target: K,) -> usize {
+        let mut s = 0;
+
+        for l in (1..self.levels.len()).rev() {
+            let below = &self.levels[l - 1];
+            let (lo, hi,) = self.window(&self.levels[l], s, target, below.keys.len(),);
+
+            s = (lo + basics::upper_bound(&below.keys[lo..hi], &target,))
+                .saturating_sub(1,);
+        }
+
+        s
+    }
+}
+
+
+macro_rules! impl_bound_learned {
+    ($(#[$attr:meta])* $name:ident) => {
+        impl<K: Numeric,> LearnedIndex<K,> {
+            $(#[$attr])*
+            #[must_use]
+            #[inline(always)]
+            pub fn $name(&self, data: &[K], target: &K,) -> usize {
+                if self.n == 0 {
+                    return 0;
+                }
+
+                let level = &self.levels[0];
+                let s = self.segment(*target,);
+                let (lo, hi,) = self.window(level, s, *target, self.n,);
+
+                let res = lo + basics::$name(&data[lo..hi], target,);
+
+                // Duplicates share the position of their first occurrence, so the
+                // prediction for a target right after a long run can fall short.
+                let end = level.starts.get(s + 1,).copied().unwrap_or(self.n,);
+                if res == hi && hi < end {
+                    return hi + basics::$name(&data[hi..end], target,);
+                }
+
+                res
+            }
+        }
+    };
+}
+
+impl_bound_learned!(lower_bound);
+impl_bound_learned!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound
+);
+
+
+#[cfg(test)]
+mod tests {
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn test_lower_bound_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+        let index = LearnedIndex::new(&data, 1,);
+
+        assert_eq!(index.lower_bound(&data, &20,), 1);
+        assert_eq!(index.lower_bound(&data, &30,), 4);
+        assert_eq!(index.lower_bound(&data, &25,), 4);
+        assert_eq!(index.lower_bound(&data, &5,), 0);
+        assert_eq!(index.lower_bound(&data, &50,), 6);
+    }
+
+    #[test]
+    fn test_upper_bound_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+        let index = LearnedIndex::new(&data, 1,);
+
+        assert_eq!(index.upper_bound(&data, &20,), 4);
+        assert_eq!(index.upper_bound(&data, &30,), 5);
+        assert_eq!(index.upper_bound(&data, &25,), 4);
+        assert_eq!(index.upper_bound(&data, &50,), 6);
+    }
+
+    #[test]
+    fn test_empty_array() {
+        let empty: Vec<i32,> = vec![];
+        let index = LearnedIndex::new(&empty, 8,);
+
+        assert_eq!(index.lower_bound(&empty, &10,), 0);
+        assert_eq!(index.upper_bound(&empty, &10,), 0);
+        assert_eq!(index.segments(), 0);
+    }
+
+    #[test]
+    fn test_model_size() {
+        // A straight line is a single segment
+        let data: Vec<i32,> = (0..100_000).map(|x| 3 * x,).collect();
+        let index = LearnedIndex::new(&data, 4,);
+
+        assert_eq!(index.segments(), 1);
+        assert_eq!(index.size_bytes(), 4 + 8 + 8);
+
+        // Uniform keys need few segments, stacked in several levels
+        let mut rng = rand::rng();
+        let mut data: Vec<i32,> =
+            (0..100_000).map(|_| rng.random_range(0..i32::MAX,),).collect();
+        data.sort_unstable();
+        let index = LearnedIndex::new(&data, 4,);
+
+        assert!(index.levels.len() > 1);
+        assert!(index.segments() < data.len() / 10);
+    }
+
+    fn check<K: Numeric + std::fmt::Debug,>(data: &[K], targets: &[K], epsilon: usize,) {
+        let index = LearnedIndex::new(data, epsilon,);
+
+        for target in targets {
+            assert_eq!(
+                index.lower_bound(data, target,),
+                basics::lower_bound(data, target,),
+                "Lower bound mismatch for target {:?}, epsilon = {}",
+                target,
+                epsilon
+            );
+            assert_eq!(
+                index.upper_bound(data, target,),
+                basics::upper_bound(data, target,),
+                "Upper bound mismatch for target {:?}, epsilon = {}",
+                target,
+                epsilon
+            );
+        }
+    }
+
+    #[test]
+    fn test_bound_fuzzy() {
+        let mut rng = rand::rng();
+
+        for epsilon in [0, 1, 4, 16, 64,] {
+            // Uniform keys
+            let mut data: Vec<i32,> =
+                (0..20_000).map(|_| rng.random_range(0..1_000_000,),).collect();
+            data.sort_unstable();
+            let targets: Vec<i32,> =
+                (0..2000).map(|_| rng.random_range(-10..1_000_010,),).collect();
+            check(&data, &targets, epsilon,);
+
+            // Heavy duplicates
+            let mut data: Vec<i32,> =
+                (0..20_000).map(|_| rng.random_range(0..50,),).collect();
+            data.sort_unstable();
+            let targets: Vec<i32,> = (-2..52).collect();
+            check(&data, &targets, epsilon,);
+
+            // Skewed keys
+            let mut data: Vec<u64,> = (0..20_000)
+                .map(|_| rng.random_range(0..64u32,),)
+                .map(|e| 1u64 << e,)
+                .collect();
+            data.extend((0..20_000).map(|_| rng.random::<u64>(),),);
+            data.sort_unstable();
+            let targets: Vec<u64,> = data
+                .iter()
+                .step_by(13,)
+                .copied()
+                .chain((0..2000).map(|_| rng.random(),),)
+                .collect();
+            check(&data, &targets, epsilon,);
+
+            // Floats
+            let mut data: Vec<f64,> =
+                (0..20_000).map(|_| rng.random_range(-1e9..1e9,),).collect();
+            data.sort_unstable_by(|a, b| a.total_cmp(b,),);
+            let targets: Vec<f64,> =
+                (0..2000).map(|_| rng.random_range(-1.1e9..1.1e9,),).collect();
+            check(&data, &targets, epsilon,);
+        }
+    }
+}
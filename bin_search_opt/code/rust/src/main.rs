@@ -1,12 +1,50 @@
+mod backend;
 mod basics;
 mod eytzinger;
+mod learned;
+mod map;
+#[cfg(unix)]
+mod pages;
+mod set;
+mod splus;
+#[cfg(unix)]
+mod storage;
 mod stree;
+mod updatable;
+mod veb;
 
-use std::time::Instant;
+use std::{collections::BTreeSet, time::Instant};
 
 use rand::{RngExt, SeedableRng};
 
-use crate::{eytzinger::create_layout_eytzinger, stree::create_layout_stree};
+use crate::{
+    backend::Backend,
+    basics::Direction,
+    eytzinger::{
+        Prefetch,
+        create_layout_eytzinger,
+        create_layout_eytzinger_blocked,
+        create_layout_eytzinger_in_place,
+        create_layout_eytzinger_iterative,
+        create_layout_eytzinger_parallel,
+    },
+    learned::LearnedIndex,
+    map::{Payload, StaticMap},
+    set::StaticSet,
+    splus::{Layers, create_layout_splus},
+    stree::{
+        create_layout_stree,
+        create_layout_stree_in_place,
+        create_layout_stree_parallel,
+    },
+    updatable::{Base, UpdatableIndex},
+    veb::create_layout_veb,
+};
+#[cfg(unix)]
+use crate::{
+    pages::{PageBuffer, Pages},
+    storage::MappedLayout,
+};
 
 
 pub fn verify_integrity() {
@@ -18,7 +56,15 @@ pub fn verify_integrity() {
 
     // 2. Build Layouts
     let eytz = create_layout_eytzinger(&data,);
-    let stree = create_layout_stree(&data,);
+    let veb = create_layout_veb(&data,);
+    let learned = LearnedIndex::new(&data, 32,);
+    let stree = create_layout_stree::<_, 16,>(&data,);
+    let splus = create_layout_splus::<_, 16,>(&data,);
+    let splus_layers = Layers::new(data.len(), 16,);
+
+    let (mut previous_target, mut previous_idx,) = (0, 0,);
+    let mut eytz_finger = eytzinger::Finger::new();
+    let mut stree_finger = stree::Finger::new();
 
     let queries = 10_000;
     for _ in 0..queries {
@@ -28,11 +74,57 @@ pub fn verify_integrity() {
         let truth_val =
             if truth_idx < data.len() { Some(data[truth_idx],) } else { None };
 
+        let direction = if target >= previous_target {
+            Direction::Forward
+        } else {
+            Direction::Backward
+        };
+        let exponential_rank =
+            basics::lower_bound_exponential(&data, &target, previous_idx, direction,);
+        (previous_target, previous_idx,) = (target, truth_idx,);
+
+        let interpolation_ranks = [
+            basics::lower_bound_interpolation(&data, &target,),
+            basics::lower_bound_interpolation_sequential(&data, &target,),
+            basics::lower_bound_interpolation_guarded(&data, &target,),
+        ];
+
         let eytz_idx = eytzinger::lower_bound_prefetched_branchless(&eytz, &target,);
         let eytz_val = eytz_idx.map(|i| eytz[i],);
+        let eytz_rank = eytzinger::lower_bound_rank(&eytz, &target,);
+        let eytz_finger_idx =
+            eytzinger::lower_bound_finger(&eytz, &target, &mut eytz_finger,);
+
+        let veb_idx = veb::lower_bound(&veb, &target,);
+        let veb_val = veb_idx.map(|i| veb[i],);
 
-        let stree_idx = stree::lower_bound(&stree, &target,);
+        let stree_idx = stree::lower_bound::<_, 16,>(&stree, &target,);
         let stree_val = stree_idx.map(|i| stree[i],);
+        let stree_rank = stree::lower_bound_rank::<_, 16,>(&stree, data.len(), &target,);
+        let stree_finger_idx = stree::lower_bound_finger::<_, 16,>(
+            &stree,
+            data.len(),
+            &target,
+            &mut stree_finger,
+        );
+
+        let splus_rank = splus::lower_bound::<_, 16,>(&splus, &splus_layers, &target,);
+
+        let learned_rank = learned.lower_bound(&data, &target,);
+
+        if exponential_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: Exponential mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, exponential_rank
+            );
+        }
+
+        if interpolation_ranks.iter().any(|&rank| rank != truth_idx,) {
+            panic!(
+                "INTEGRITY FAILURE: Interpolation mismatch.\nTarget: {}\nExpected: {}\nGot: {:?}",
+                target, truth_idx, interpolation_ranks
+            );
+        }
 
         if eytz_val != truth_val {
             panic!(
@@ -41,12 +133,349 @@ pub fn verify_integrity() {
             );
         }
 
+        if eytz_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: Eytzinger rank mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, eytz_rank
+            );
+        }
+
+        if veb_val != truth_val {
+            panic!(
+                "INTEGRITY FAILURE: vEB mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                target, truth_val, veb_val
+            );
+        }
+
+        if eytz_finger_idx != eytz_idx {
+            panic!(
+                "INTEGRITY FAILURE: Eytzinger finger mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                target, eytz_idx, eytz_finger_idx
+            );
+        }
+
         if stree_val != truth_val {
             panic!(
                 "INTEGRITY FAILURE: S-Tree mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
                 target, truth_val, stree_val
             );
         }
+
+        if stree_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: S-Tree rank mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, stree_rank
+            );
+        }
+
+        if stree_finger_idx != stree_idx {
+            panic!(
+                "INTEGRITY FAILURE: S-Tree finger mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                target, stree_idx, stree_finger_idx
+            );
+        }
+
+        if splus_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: S+ Tree mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, splus_rank
+            );
+        }
+
+        if learned_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: Learned index mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, learned_rank
+            );
+        }
+    }
+
+    // S-Tree over the other key types: u64 ids and f64 timestamps
+    let mut ids: Vec<u64,> = (0..n).map(|_| rng.random(),).collect();
+    ids.sort_unstable();
+    let ids_stree = create_layout_stree::<_, 8,>(&ids,);
+
+    let mut timestamps: Vec<f64,> =
+        (0..n).map(|_| rng.random_range(0.0..1e9,),).collect();
+    timestamps.sort_unstable_by(|a, b| a.total_cmp(b,),);
+    let timestamps_stree = create_layout_stree::<_, 8,>(&timestamps,);
+
+    for _ in 0..queries {
+        let target: u64 = rng.random();
+
+        let truth_idx = basics::lower_bound(&ids, &target,);
+        let stree_rank =
+            stree::lower_bound_rank::<_, 8,>(&ids_stree, ids.len(), &target,);
+
+        if stree_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: S-Tree u64 mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, stree_rank
+            );
+        }
+
+        let target = rng.random_range(0.0..1e9,);
+
+        let truth_idx = basics::lower_bound(&timestamps, &target,);
+        let stree_rank = stree::lower_bound_rank::<_, 8,>(
+            &timestamps_stree,
+            timestamps.len(),
+            &target,
+        );
+
+        if stree_rank != truth_idx {
+            panic!(
+                "INTEGRITY FAILURE: S-Tree f64 mismatch.\nTarget: {}\nExpected: {}\nGot: {}",
+                target, truth_idx, stree_rank
+            );
+        }
+    }
+
+    // Equal ranges and counts over heavily duplicated keys
+    let mut dups: Vec<i32,> = (0..n).map(|_| rng.random_range(0..1_000,),).collect();
+    dups.sort_unstable();
+    let dups_eytz = create_layout_eytzinger(&dups,);
+    let dups_stree = create_layout_stree::<_, 16,>(&dups,);
+    let dups_splus = create_layout_splus::<_, 16,>(&dups,);
+    let dups_layers = Layers::new(dups.len(), 16,);
+
+    for target in -1..1_001 {
+        let truth = basics::equal_range(&dups, &target,);
+        let ranges = [
+            eytzinger::equal_range(&dups_eytz, &target,),
+            stree::equal_range::<_, 16,>(&dups_stree, dups.len(), &target,),
+            splus::equal_range::<_, 16,>(&dups_splus, &dups_layers, &target,),
+        ];
+
+        let truth_count = basics::count(&dups, &target,);
+        let counts = [
+            eytzinger::count(&dups_eytz, &target,),
+            stree::count::<_, 16,>(&dups_stree, dups.len(), &target,),
+            splus::count::<_, 16,>(&dups_splus, &dups_layers, &target,),
+        ];
+
+        let (lo, hi,) = (target, target + rng.random_range(0..100,),);
+        let truth_in_range = basics::count_in_range(&dups, &lo, &hi,);
+        let in_range = [
+            eytzinger::count_in_range(&dups_eytz, &lo, &hi,),
+            stree::count_in_range::<_, 16,>(&dups_stree, dups.len(), &lo, &hi,),
+            splus::count_in_range::<_, 16,>(&dups_splus, &dups_layers, &lo, &hi,),
+        ];
+
+        if ranges.iter().any(|&range| range != truth,)
+            || counts.iter().any(|&count| count != truth_count,)
+            || in_range.iter().any(|&count| count != truth_in_range,)
+        {
+            panic!(
+                "INTEGRITY FAILURE: Equal range mismatch.\nTarget: {}\nExpected: {:?}, {}, {}\nGot: {:?}, {:?}, {:?}",
+                target, truth, truth_count, truth_in_range, ranges, counts, in_range
+            );
+        }
+    }
+
+    // Updatable index under a trickle of inserts and deletes, against a plain sorted
+    // vector
+    for kind in [Base::Eytzinger, Base::STree,] {
+        let mut logical = data.clone();
+        let mut index = UpdatableIndex::new(&logical, kind, 1_000,);
+
+        for _ in 0..queries {
+            let key = rng.random_range(0..i32::MAX,);
+            let existing = logical[rng.random_range(0..logical.len(),)];
+
+            index.insert(key,);
+            logical.insert(basics::upper_bound(&logical, &key,), key,);
+
+            index.remove(&existing,);
+            logical.remove(basics::lower_bound(&logical, &existing,),);
+
+            let target = rng.random_range(0..i32::MAX,);
+            let truth_idx = basics::lower_bound(&logical, &target,);
+            let truth_upper = basics::upper_bound(&logical, &target,);
+            let index_ranks = (index.lower_bound(&target,), index.upper_bound(&target,),);
+
+            if index_ranks != (truth_idx, truth_upper,) {
+                panic!(
+                    "INTEGRITY FAILURE: Updatable {:?} mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                    kind,
+                    target,
+                    (truth_idx, truth_upper,),
+                    index_ranks
+                );
+            }
+        }
+    }
+
+    // Static maps, against the payload of the sorted distinct keys
+    let mut keys = data.clone();
+    keys.dedup();
+    let values: Vec<usize,> = (0..keys.len()).collect();
+
+    for kind in [Base::Eytzinger, Base::STree,] {
+        for payload in [Payload::Interleaved, Payload::Separate,] {
+            let mut map = StaticMap::new(&keys, &values, kind, payload,);
+
+            if map.len() != keys.len()
+                || !map.iter().map(|(_, &v,)| v,).eq(0..keys.len(),)
+            {
+                panic!(
+                    "INTEGRITY FAILURE: Static map {:?} {:?} iteration mismatch.",
+                    kind, payload
+                );
+            }
+
+            for _ in 0..queries {
+                let key = keys[rng.random_range(0..keys.len(),)];
+                let (lo, hi,) =
+                    (rng.random_range(0..i32::MAX,), rng.random_range(0..i32::MAX,),);
+
+                // The value is bumped in place, then restored
+                if let Some(value,) = map.get_mut(&key,) {
+                    *value += 1;
+                }
+
+                let truth = (
+                    Some(basics::lower_bound(&keys, &key,) + 1,),
+                    basics::count_in_range(&keys, &lo, &hi,),
+                );
+                let got = (map.get(&key,).copied(), map.range(&lo, &hi,).count(),);
+
+                if let Some(value,) = map.get_mut(&key,) {
+                    *value -= 1;
+                }
+
+                if got != truth {
+                    panic!(
+                        "INTEGRITY FAILURE: Static map {:?} {:?} mismatch.\nKey: {}, range: [{}, {})\nExpected: {:?}\nGot: {:?}",
+                        kind, payload, key, lo, hi, truth, got
+                    );
+                }
+            }
+        }
+    }
+
+    // Static sets, against the standard ordered set
+    let (left, right,) = data.split_at(data.len() / 2,);
+    let right: Vec<i32,> =
+        right.iter().step_by(3,).chain(&left[..1_000],).copied().collect();
+    let truth: (BTreeSet<i32,>, BTreeSet<i32,>,) =
+        (left.iter().copied().collect(), right.iter().copied().collect(),);
+
+    for kind in [Base::Eytzinger, Base::STree,] {
+        let sets = (
+            StaticSet::new(left.iter().copied(), kind,),
+            StaticSet::new(right.clone(), kind,),
+        );
+
+        let targets: Vec<i32,> = (0..queries)
+            .map(|_| rng.random_range(0..i32::MAX,),)
+            .chain(right.iter().copied().take(queries,),)
+            .collect();
+        let mut results = vec![false; targets.len()];
+        sets.0.contains_many(&targets, &mut results,);
+
+        for (target, &result,) in targets.iter().zip(&results,) {
+            let expected = truth.0.contains(target,);
+            if sets.0.contains(target,) != expected || result != expected {
+                panic!(
+                    "INTEGRITY FAILURE: Static set {:?} membership mismatch.\nTarget: {}\nExpected: {}",
+                    kind, target, expected
+                );
+            }
+        }
+
+        if sets.0.len() != truth.0.len()
+            || !sets
+                .0
+                .intersection(&sets.1,)
+                .eq(truth.0.intersection(&truth.1,).copied(),)
+            || !sets.0.difference(&sets.1,).eq(truth.0.difference(&truth.1,).copied(),)
+            || !sets.0.union(&sets.1,).eq(truth.0.union(&truth.1,).copied(),)
+        {
+            panic!("INTEGRITY FAILURE: Static set {:?} operation mismatch.", kind);
+        }
+    }
+
+    // Every supported backend, forced on the searches and on the indexes, against the
+    // plain sorted data
+    for backend in Backend::supported() {
+        let set =
+            StaticSet::new(keys.iter().copied(), Base::STree,).with_backend(backend,);
+        let map = StaticMap::new(&keys, &keys, Base::STree, Payload::Separate,)
+            .with_backend(backend,);
+        let index =
+            UpdatableIndex::new(&data, Base::Eytzinger, 1_000,).with_backend(backend,);
+
+        if (set.backend(), map.backend(), index.backend(),)
+            != (backend, backend, backend,)
+        {
+            panic!("INTEGRITY FAILURE: {:?} backend not kept by the indexes.", backend);
+        }
+
+        for _ in 0..queries {
+            let target = rng.random_range(0..i32::MAX,);
+            let truth = (
+                basics::lower_bound(&data, &target,),
+                basics::upper_bound(&data, &target,),
+                keys.binary_search(&target,).is_ok(),
+            );
+            let got = (
+                stree::lower_bound_rank_with::<_, 16,>(
+                    backend,
+                    &stree,
+                    data.len(),
+                    &target,
+                ),
+                index.upper_bound(&target,),
+                set.contains(&target,) && map.get(&target,) == Some(&target,),
+            );
+
+            if got != truth {
+                panic!(
+                    "INTEGRITY FAILURE: {:?} backend mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                    backend, target, truth, got
+                );
+            }
+        }
+    }
+
+    // Layouts saved to disk and searched through their mapping
+    #[cfg(unix)]
+    {
+        let eytz_path = std::env::temp_dir().join("verify-eytzinger.layout",);
+        let stree_path = std::env::temp_dir().join("verify-stree.layout",);
+        storage::save_eytzinger(&eytz_path, &eytz,)
+            .expect("cannot save the Eytzinger layout",);
+        storage::save_stree::<_, 16,>(&stree_path, &stree, data.len(),)
+            .expect("cannot save the S-tree layout",);
+
+        let mapped_eytz = MappedLayout::<i32,>::open_eytzinger(&eytz_path,)
+            .expect("cannot load the Eytzinger layout",);
+        let mapped_stree = MappedLayout::<i32,>::open_stree::<16,>(&stree_path,)
+            .expect("cannot load the S-tree layout",);
+
+        for _ in 0..queries {
+            let target = rng.random_range(0..i32::MAX,);
+            let truth = (
+                eytzinger::lower_bound_prefetched_branchless(&eytz, &target,),
+                stree::lower_bound::<_, 16,>(&stree, &target,),
+            );
+            let mapped = (
+                eytzinger::lower_bound_prefetched_branchless(mapped_eytz.keys(), &target,),
+                stree::lower_bound::<_, 16,>(mapped_stree.keys(), &target,),
+            );
+
+            if mapped != truth || mapped_stree.len() != data.len() {
+                panic!(
+                    "INTEGRITY FAILURE: Mapped layout mismatch.\nTarget: {}\nExpected: {:?}\nGot: {:?}",
+                    target, truth, mapped
+                );
+            }
+        }
+
+        drop((mapped_eytz, mapped_stree,),);
+        std::fs::remove_file(eytz_path,).expect("cannot remove the Eytzinger layout",);
+        std::fs::remove_file(stree_path,).expect("cannot remove the S-tree layout",);
     }
 
     println!("Integrity Check Passed: All algorithms match.");
@@ -70,6 +499,10 @@ pub fn benchmark_performance(n: usize,) {
     let targets: Vec<i32,> =
         (0..queries).map(|_| rng.random_range(0..i32::MAX,),).collect();
 
+    // Same queries in increasing order, for the searches starting from a hint
+    let mut sorted_targets = targets.clone();
+    sorted_targets.sort_unstable();
+
     // Generating data for basic binary search
     let mut rng = rand::prelude::StdRng::seed_from_u64(222,);
     let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..i32::MAX,),).collect();
@@ -95,8 +528,162 @@ pub fn benchmark_performance(n: usize,) {
     println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
 
 
+    println!("\nBenchmark interpolation lower bound:");
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += basics::lower_bound_interpolation(&data, target,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!("\nBenchmark interpolation-sequential lower bound:");
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += basics::lower_bound_interpolation_sequential(&data, target,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!("\nBenchmark guarded interpolation lower bound:");
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += basics::lower_bound_interpolation_guarded(&data, target,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!(
+        "\nBenchmark exponential lower bound (sorted queries, previous result as hint):"
+    );
+
+    let start = Instant::now();
+
+    let mut hint = 0;
+    for target in &sorted_targets {
+        hint = basics::lower_bound_exponential(&data, target, hint, Direction::Forward,);
+        checksum += hint;
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    let mut ranks = vec![0; queries];
+
+    println!("\nBenchmark sorted batch lower bound (sorted queries):");
+
+    let start = Instant::now();
+
+    basics::lower_bound_sorted_batch(&data, &sorted_targets, &mut ranks,);
+    checksum += ranks.iter().sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!(
+        "\nBenchmark sorted batch lower bound (queries sorted then scattered back):"
+    );
+
+    let start = Instant::now();
+
+    basics::with_sorted_queries(&targets, &mut ranks, |targets, ranks| {
+        basics::lower_bound_sorted_batch(&data, targets, ranks,)
+    },);
+    checksum += ranks.iter().sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    // Fitting the learned index over the sorted data
+    let build_start = Instant::now();
+    let learned = LearnedIndex::new(&data, 32,);
+    let build_duration = build_start.elapsed();
+
+    println!("\nBenchmark learned index lower bound:");
+    println!("  -> Construction: {:.2?}", build_duration);
+    println!(
+        "  -> Model size: {} segments ({:.2} KB)",
+        learned.segments(),
+        learned.size_bytes() as f64 / 1_024.0
+    );
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += learned.lower_bound(&data, target,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+    drop(learned,);
+
+
+    // Comparing the Eytzinger builders, each result is dropped before the next one
+    println!("\nBenchmark Eytzinger construction:");
+
+    let build_start = Instant::now();
+    let layout = create_layout_eytzinger_iterative(&data,);
+    println!("  -> Iterative:    {:.2?}", build_start.elapsed());
+    drop(layout,);
+
+    let build_start = Instant::now();
+    let layout = create_layout_eytzinger_blocked(&data,);
+    println!("  -> Blocked:      {:.2?}", build_start.elapsed());
+    drop(layout,);
+
+    let mut layout = data.clone();
+    let build_start = Instant::now();
+    create_layout_eytzinger_in_place(&mut layout,);
+    println!("  -> In place:     {:.2?}", build_start.elapsed());
+    drop(layout,);
+
+    let threads = std::thread::available_parallelism().map_or(1, |t| t.get(),);
+
+    let build_start = Instant::now();
+    let layout = create_layout_eytzinger_parallel(&data, threads,);
+    println!("  -> Parallel ({} threads): {:.2?}", threads, build_start.elapsed());
+    drop(layout,);
+
     // Ordering data for Eytzinger version
+    let build_start = Instant::now();
     let data_eytzinger = create_layout_eytzinger(&data,);
+    println!("  -> Recursive:    {:.2?}", build_start.elapsed());
 
     println!("\nBenchmark Eytzinger prefetched branchless lower bound:");
 
@@ -114,17 +701,461 @@ pub fn benchmark_performance(n: usize,) {
     println!("  -> Total time: {:.2?}", duration);
     println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
 
+    let single_ns_per_op = ns_per_op;
+
+    for prefetch in [Prefetch::Disabled, Prefetch::Lookahead,] {
+        println!(
+            "\nBenchmark Eytzinger branchless lower bound, {:?} prefetch:",
+            prefetch
+        );
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum += prefetch.lower_bound(&data_eytzinger, target,).unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+
+    let mut results = vec![None; queries];
+
+    println!("\nBenchmark Eytzinger batched lower bound (groups of 16):");
+
+    let start = Instant::now();
+
+    eytzinger::lower_bound_batch::<_, _, 16,>(&data_eytzinger, &targets, &mut results,);
+    checksum += results.iter().map(|res| res.unwrap_or(0,),).sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    println!("  -> Speedup vs single query: {:.2}x", single_ns_per_op / ns_per_op);
+
+
+    println!("\nBenchmark Eytzinger batched lower bound (groups of 64):");
+
+    let start = Instant::now();
+
+    eytzinger::lower_bound_batch::<_, _, 64,>(&data_eytzinger, &targets, &mut results,);
+    checksum += results.iter().map(|res| res.unwrap_or(0,),).sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    println!("  -> Speedup vs single query: {:.2}x", single_ns_per_op / ns_per_op);
+
+
+    println!("\nBenchmark Eytzinger vertical SIMD lower bound (8 queries per register):");
+
+    let start = Instant::now();
+
+    eytzinger::lower_bound_vertical(&data_eytzinger, &targets, &mut results,);
+    checksum += results.iter().map(|res| res.unwrap_or(0,),).sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    println!("  -> Speedup vs single query: {:.2}x", single_ns_per_op / ns_per_op);
+
+
+    drop(results,);
+
+
+    println!("\nBenchmark Eytzinger sorted batch lower bound (sorted queries):");
+
+    let start = Instant::now();
+
+    eytzinger::lower_bound_sorted_batch(&data_eytzinger, &sorted_targets, &mut ranks,);
+    checksum += ranks.iter().sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!("\nBenchmark Eytzinger finger lower bound (sorted queries):");
+
+    let start = Instant::now();
+
+    let mut finger = eytzinger::Finger::new();
+    for target in &sorted_targets {
+        checksum += eytzinger::lower_bound_finger(&data_eytzinger, target, &mut finger,)
+            .unwrap_or(0,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    // Same search over a copy of the layout, backed by each kind of pages
+    #[cfg(unix)]
+    for pages in [Pages::Base, Pages::Transparent, Pages::HugeTlb,] {
+        let layout =
+            PageBuffer::from_fn(pages, data_eytzinger.len(), |i| data_eytzinger[i],);
+
+        println!(
+            "\nBenchmark Eytzinger prefetched branchless lower bound, {:?} pages:",
+            pages
+        );
+        println!(
+            "  -> Pages:      {:?}, {} KB",
+            layout.pages(),
+            layout.page_size() >> 10
+        );
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum += eytzinger::lower_bound_prefetched_branchless(&layout, target,)
+                .unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+    drop(data_eytzinger,);
+
+
+    // Ordering data for van Emde Boas version
+    let build_start = Instant::now();
+    let data_veb = create_layout_veb(&data,);
+    let build_duration = build_start.elapsed();
+
+    println!("\nBenchmark van Emde Boas lower bound:");
+    println!("  -> Construction: {:.2?}", build_duration);
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += veb::lower_bound(&data_veb, target,).unwrap_or(0,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+    drop(data_veb,);
+
+
+    for kind in [Base::Eytzinger, Base::STree,] {
+        let set = StaticSet::new(data.iter().copied(), kind,);
+
+        println!("\nBenchmark static set contains, {:?}:", kind);
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum += set.contains(target,) as usize;
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+        let mut results = vec![false; queries];
+
+        println!("\nBenchmark static set contains many, {:?}:", kind);
+
+        let start = Instant::now();
+
+        set.contains_many(&targets, &mut results,);
+        checksum += results.iter().filter(|&&found| found,).count();
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+
+    // Ordering data for S+ tree version
+    let build_start = Instant::now();
+    let data_splus = create_layout_splus::<_, 16,>(&data,);
+    let layers = Layers::new(n, 16,);
+    let build_duration = build_start.elapsed();
+
+    println!("\nBenchmark S+ tree simd lower bound:");
+    println!("  -> Construction: {:.2?}", build_duration);
+
+    let start = Instant::now();
+
+    for target in &targets {
+        checksum += splus::lower_bound::<_, 16,>(&data_splus, &layers, target,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+    drop(data_splus,);
+
 
     // Ordering data for stree version
-    let data_stree = create_layout_stree(&data,);
-    drop(data,);
+    let build_start = Instant::now();
+    let data_stree = create_layout_stree::<_, 16,>(&data,);
+    let build_duration = build_start.elapsed();
 
     println!("\nBenchmark stree simd lower bound:");
+    println!("  -> Construction: {:.2?}", build_duration);
+
+    // Room for the padding, so that the build never holds two copies of the keys
+    let mut layout = Vec::with_capacity(n + 15,);
+    layout.extend_from_slice(&data,);
+    let build_start = Instant::now();
+    create_layout_stree_in_place::<_, 16,>(&mut layout,);
+    println!("  -> In place:     {:.2?}", build_start.elapsed());
+    drop(layout,);
+
+    let build_start = Instant::now();
+    let layout = create_layout_stree_parallel::<_, 16,>(&data, threads,);
+    println!("  -> Parallel ({} threads): {:.2?}", threads, build_start.elapsed());
+    drop(layout,);
 
     let start = Instant::now();
 
     for target in &targets {
-        checksum += stree::lower_bound(&data_stree, target,).unwrap_or(0,);
+        checksum += stree::lower_bound::<_, 16,>(&data_stree, target,).unwrap_or(0,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+    let single_ns_per_op = ns_per_op;
+
+    // The same search forced on each backend the CPU supports
+    for backend in Backend::supported() {
+        println!("\nBenchmark stree simd lower bound, {:?} kernels:", backend);
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum += stree::lower_bound_with::<_, 16,>(backend, &data_stree, target,)
+                .unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+    // 32 keys nodes, their two AVX-512 masks merged before a single popcount
+    let data_stree_32 = create_layout_stree::<_, 32,>(&data,);
+
+    for backend in Backend::supported() {
+        println!(
+            "\nBenchmark stree simd lower bound, 32 keys nodes, {:?} kernels:",
+            backend
+        );
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum +=
+                stree::lower_bound_with::<_, 32,>(backend, &data_stree_32, target,)
+                    .unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+    drop(data_stree_32,);
+
+
+    let mut results = vec![None; queries];
+
+    println!("\nBenchmark stree pipelined lower bound (8 queries in flight):");
+
+    let start = Instant::now();
+
+    stree::lower_bound_batch::<_, 16, 8,>(&data_stree, &targets, &mut results,);
+    checksum += results.iter().map(|res| res.unwrap_or(0,),).sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    println!("  -> Speedup vs single query: {:.2}x", single_ns_per_op / ns_per_op);
+
+
+    println!("\nBenchmark stree pipelined lower bound (16 queries in flight):");
+
+    let start = Instant::now();
+
+    stree::lower_bound_batch::<_, 16, 16,>(&data_stree, &targets, &mut results,);
+    checksum += results.iter().map(|res| res.unwrap_or(0,),).sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    println!("  -> Speedup vs single query: {:.2}x", single_ns_per_op / ns_per_op);
+
+
+    drop(results,);
+
+
+    println!("\nBenchmark stree sorted batch lower bound (sorted queries):");
+
+    let start = Instant::now();
+
+    stree::lower_bound_sorted_batch::<_, 16,>(
+        &data_stree,
+        n,
+        &sorted_targets,
+        &mut ranks,
+    );
+    checksum += ranks.iter().sum::<usize>();
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    println!("\nBenchmark stree finger lower bound (sorted queries):");
+
+    let start = Instant::now();
+
+    let mut finger = stree::Finger::new();
+    for target in &sorted_targets {
+        checksum +=
+            stree::lower_bound_finger::<_, 16,>(&data_stree, n, target, &mut finger,)
+                .unwrap_or(0,);
+    }
+
+    let duration = start.elapsed();
+    let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+    println!("  -> Total time: {:.2?}", duration);
+    println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+
+    // Same search over a copy of the layout, backed by each kind of pages
+    #[cfg(unix)]
+    for pages in [Pages::Base, Pages::Transparent, Pages::HugeTlb,] {
+        let layout = PageBuffer::from_fn(pages, data_stree.len(), |i| data_stree[i],);
+
+        println!("\nBenchmark stree simd lower bound, {:?} pages:", pages);
+        println!(
+            "  -> Pages:      {:?}, {} KB",
+            layout.pages(),
+            layout.page_size() >> 10
+        );
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum += stree::lower_bound::<_, 16,>(&layout, target,).unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+    }
+
+
+    // Saving the stree layout, then searching it straight from its mapping. The file is
+    // as large as the layout, and the temporary directory is often held in memory, so
+    // only the smallest run writes one.
+    #[cfg(unix)]
+    if n <= 100_000_000 {
+        let path = std::env::temp_dir().join("benchmark-stree.layout",);
+
+        let build_start = Instant::now();
+        storage::save_stree::<_, 16,>(&path, &data_stree, n,)
+            .expect("cannot save the layout",);
+        let save_duration = build_start.elapsed();
+
+        let build_start = Instant::now();
+        let mapped = MappedLayout::<i32,>::open_stree::<16,>(&path,)
+            .expect("cannot load the layout",);
+        let load_duration = build_start.elapsed();
+
+        println!("\nBenchmark mapped stree simd lower bound:");
+        println!("  -> Save:       {:.2?}", save_duration);
+        println!("  -> Load:       {:.2?}", load_duration);
+
+        let start = Instant::now();
+
+        for target in &targets {
+            checksum +=
+                stree::lower_bound::<_, 16,>(mapped.keys(), target,).unwrap_or(0,);
+        }
+
+        let duration = start.elapsed();
+        let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+        println!("  -> Total time: {:.2?}", duration);
+        println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+
+        drop(mapped,);
+        std::fs::remove_file(path,).expect("cannot remove the layout",);
+    }
+
+    drop(data_stree,);
+
+
+    // Distinct keys with a payload each, queried with keys of the map
+    let mut keys = data;
+    keys.dedup();
+    let values: Vec<u32,> = keys.iter().map(|&key| key as u32 ^ 0x5BD1_E995,).collect();
+    let hits: Vec<i32,> = targets
+        .iter()
+        .map(|target| keys[basics::lower_bound(&keys, target,).min(keys.len() - 1,)],)
+        .collect();
+
+    println!("\nBenchmark lower bound plus payload index:");
+
+    let start = Instant::now();
+
+    for target in &hits {
+        let i = basics::lower_bound(&keys, target,);
+        if keys.get(i,) == Some(target,) {
+            checksum += values[i] as usize;
+        }
     }
 
     let duration = start.elapsed();
@@ -133,11 +1164,42 @@ pub fn benchmark_performance(n: usize,) {
     println!("  -> Total time: {:.2?}", duration);
     println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
 
+    // The maps store each key as its own value, so that building them holds no other
+    // array than the keys
+    drop(values,);
+
+    for kind in [Base::Eytzinger, Base::STree,] {
+        for payload in [Payload::Interleaved, Payload::Separate,] {
+            let build_start = Instant::now();
+            let map = StaticMap::new(&keys, &keys, kind, payload,);
+            let build_duration = build_start.elapsed();
+
+            println!("\nBenchmark static map get, {:?} with {:?} values:", kind, payload);
+            println!("  -> Construction: {:.2?}", build_duration);
+
+            let start = Instant::now();
+
+            for target in &hits {
+                checksum += map.get(target,).map_or(0, |&value| value as usize,);
+            }
+
+            let duration = start.elapsed();
+            let ns_per_op = duration.as_nanos() as f64 / queries as f64;
+
+            println!("  -> Total time: {:.2?}", duration);
+            println!("  -> Latency:    \x1b[1;32m{:.2} ns/op\x1b[0m", ns_per_op);
+        }
+    }
+
+    drop((keys, hits,),);
+
     println!("\nChecksum {}", checksum);
 }
 
 
 fn main() {
+    println!("Kernels: {:?}, supported: {:?}", Backend::current(), Backend::supported());
+
     println!("========================= INTEGRITY ==========================");
     verify_integrity();
 
@@ -0,0 +1,384 @@
+use std::{borrow::Borrow, ops::Range};
+
+use crate::{
+    backend::{Backend, Isa, dispatch},
+    eytzinger::{self, Prefetch, create_layout_eytzinger},
+    stree::{self, Kernels, Key, create_layout_stree},
+    updatable::Base,
+};
+
+
+const BLOCK: usize = 16;
+
+
+// Where the values live, relative to the keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Payload {
+    // Next to their keys: in the same entry for the Eytzinger layout, after the keys
+    // of their node for the S-tree, so that a hit fetches its value from a line which
+    // the search is likely to have just loaded.
+    Interleaved,
+    // In their own array at the positions of their keys, which keeps the searched
+    // array as dense as the plain layout.
+    Separate,
+}
+
+// Key and value of the Eytzinger interleaved layout, searched through Borrow
+struct Entry<K, V,> {
+    key:   K,
+    value: V,
+}
+
+impl<K, V,> Borrow<K,> for Entry<K, V,> {
+    fn borrow(&self,) -> &K {
+        &self.key
+    }
+}
+
+// Node of the S-tree interleaved layout, its keys stay contiguous for the kernels
+#[repr(C)]
+struct Node<K, V,> {
+    keys:   [K; BLOCK],
+    values: [V; BLOCK],
+}
+
+enum Storage<K, V,> {
+    Separate { keys: Vec<K,>, values: Vec<V,>, },
+    Entries(Vec<Entry<K, V,>,>,),
+    Nodes(Vec<Node<K, V,>,>,),
+}
+
+// Map from distinct keys to values, both laid out once for all by a static layout.
+// The layout position of a key is also the position of its value. The keys are
+// searched with the kernels of the backend current when the map is built.
+pub struct StaticMap<K: Key, V,> {
+    kind:    Base,
+    backend: Backend,
+    n:       usize,
+    storage: Storage<K, V,>,
+}
+
+
+impl<K: Key, V: Clone,> StaticMap<K, V,> {
+    #[must_use]
+    pub fn new(keys: &[K], values: &[V], kind: Base, payload: Payload,) -> Self {
+        assert!(keys.is_sorted_by(|a, b| a < b), "map keys must be sorted and distinct");
+        assert_eq!(keys.len(), values.len(), "every key needs one value");
+
+        let n = keys.len();
+        let rank = |pos: usize| match kind {
+            | Base::Eytzinger => eytzinger::index_to_rank(n, pos,),
+            | Base::STree => stree::index_to_rank::<BLOCK,>(n, pos,),
+        };
+
+        let storage = match (kind, payload,) {
+            | (_, Payload::Separate,) => {
+                let layout = match kind {
+                    | Base::Eytzinger => create_layout_eytzinger(keys,),
+                    | Base::STree => create_layout_stree::<K, BLOCK,>(keys,),
+                };
+                let values = (0..n).map(|pos| values[rank(pos,)].clone(),).collect();
+
+                Storage::Separate { keys: layout, values, }
+            },
+            | (Base::Eytzinger, Payload::Interleaved,) => Storage::Entries(
+                (0..n)
+                    .map(|pos| {
+                        let r = rank(pos,);
+                        Entry { key: keys[r], value: values[r].clone(), }
+                    },)
+                    .collect(),
+            ),
+            | (Base::STree, Payload::Interleaved,) => Storage::Nodes(
+                // The padding keys get a copy of the last value, which is never read
+                (0..n.div_ceil(BLOCK,))
+                    .map(|node| {
+                        let r = |slot: usize| {
+                            let pos = node * BLOCK + slot;
+                            if pos < n { Some(rank(pos,),) } else { None }
+                        };
+
+                        Node {
+                            keys:   std::array::from_fn(|slot| {
+                                r(slot,).map_or(K::PADDING, |r| keys[r],)
+                            },),
+                            values: std::array::from_fn(|slot| {
+                                values[r(slot,).unwrap_or(n - 1,)].clone()
+                            },),
+                        }
+                    },)
+                    .collect(),
+            ),
+        };
+
+        Self { kind, backend: Backend::current(), n, storage, }
+    }
+}
+
+
+impl<K: Key, V,> StaticMap<K, V,> {
+    // Same map searched with the kernels of backend
+    #[must_use]
+    pub fn with_backend(self, backend: Backend,) -> Self {
+        assert!(backend.is_supported(), "{:?} is not supported", backend);
+
+        Self { backend, ..self }
+    }
+
+    #[must_use]
+    pub fn backend(&self,) -> Backend {
+        self.backend
+    }
+
+    #[must_use]
+    pub fn len(&self,) -> usize {
+        self.n
+    }
+
+    #[cfg_attr(not(test), expect(unused))]
+    #[must_use]
+    pub fn is_empty(&self,) -> bool {
+        self.n == 0
+    }
+
+    #[inline(always)]
+    fn key(&self, pos: usize,) -> K {
+        match &self.storage {
+            | Storage::Separate { keys, .. } => keys[pos],
+            | Storage::Entries(entries,) => entries[pos].key,
+            | Storage::Nodes(nodes,) => nodes[pos / BLOCK].keys[pos % BLOCK],
+        }
+    }
+
+    #[inline(always)]
+    fn value(&self, pos: usize,) -> &V {
+        match &self.storage {
+            | Storage::Separate { values, .. } => &values[pos],
+            | Storage::Entries(entries,) => &entries[pos].value,
+            | Storage::Nodes(nodes,) => &nodes[pos / BLOCK].values[pos % BLOCK],
+        }
+    }
+
+    #[inline(always)]
+    fn value_mut(&mut self, pos: usize,) -> &mut V {
+        match &mut self.storage {
+            | Storage::Separate { values, .. } => &mut values[pos],
+            | Storage::Entries(entries,) => &mut entries[pos].value,
+            | Storage::Nodes(nodes,) => &mut nodes[pos / BLOCK].values[pos % BLOCK],
+        }
+    }
+
+    // Same descent as stree::lower_bound, over nodes found by node_keys. The padding is
+    // told apart by its position, so that a key equal to K::PADDING is still found.
+    #[inline(always)]
+    fn stree_position<I: Isa,>(
+        &self,
+        target: K,
+        node_keys: impl Fn(usize,) -> *const K,
+    ) -> Option<usize,>
+    where
+        K: Kernels<I,>,
+    {
+        let nodes = self.n.div_ceil(BLOCK,);
+
+        let mut res: Option<usize,> = None;
+        let mut cur = 0;
+
+        while cur < nodes {
+            let lookahead = cur * (BLOCK + 1) + I::prefetch_offset(BLOCK,);
+            if lookahead < nodes {
+                unsafe { I::prefetch(node_keys(lookahead,) as *const i8,) };
+            }
+
+            let i = unsafe {
+                <K as Kernels<I,>>::node_lower::<BLOCK,>(node_keys(cur,), target,)
+            };
+
+            if i < BLOCK && cur * BLOCK + i < self.n {
+                res = Some(cur * BLOCK + i,);
+            }
+
+            cur = cur * (BLOCK + 1) + i + 1;
+        }
+
+        res
+    }
+
+    // Layout position of the first key not less than target
+    #[inline(always)]
+    fn position(&self, target: &K,) -> Option<usize,> {
+        let prefetch = Prefetch::of(self.backend,);
+
+        match &self.storage {
+            | Storage::Separate { keys, .. } => match self.kind {
+                | Base::Eytzinger => prefetch.lower_bound(keys, target,),
+                | Base::STree => dispatch!(self.backend, I => {
+                    self.stree_position::<I,>(*target, |node| unsafe {
+                        keys.as_ptr().add(node * BLOCK,)
+                    },)
+                }),
+            },
+            | Storage::Entries(entries,) => prefetch.lower_bound(entries, target,),
+            | Storage::Nodes(nodes,) => dispatch!(self.backend, I => {
+                self.stree_position::<I,>(*target, |node| nodes[node].keys.as_ptr(),)
+            }),
+        }
+    }
+
+    #[inline(always)]
+    fn rank(&self, pos: usize,) -> usize {
+        match self.kind {
+            | Base::Eytzinger => eytzinger::index_to_rank(self.n, pos,),
+            | Base::STree => stree::index_to_rank::<BLOCK,>(self.n, pos,),
+        }
+    }
+
+    #[inline(always)]
+    fn index(&self, rank: usize,) -> usize {
+        match self.kind {
+            | Base::Eytzinger => eytzinger::rank_to_index(self.n, rank,),
+            | Base::STree => stree::rank_to_index::<BLOCK,>(self.n, rank,),
+        }
+    }
+
+    #[inline(always)]
+    fn find(&self, key: &K,) -> Option<usize,> {
+        self.position(key,).filter(|&pos| self.key(pos,) == *key,)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn get(&self, key: &K,) -> Option<&V,> {
+        self.find(key,).map(|pos| self.value(pos,),)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn get_mut(&mut self, key: &K,) -> Option<&mut V,> {
+        self.find(key,).map(|pos| self.value_mut(pos,),)
+    }
+
+    fn entries(&self, ranks: Range<usize,>,) -> impl Iterator<Item = (K, &V,),> {
+        ranks.map(|rank| {
+            let pos = self.index(rank,);
+            (self.key(pos,), self.value(pos,),)
+        },)
+    }
+
+    // Entries whose key is in the half-open range [lo, hi), in key order
+    pub fn range(&self, lo: &K, hi: &K,) -> impl Iterator<Item = (K, &V,),> {
+        let rank = |target| self.position(target,).map_or(self.n, |pos| self.rank(pos,),);
+        let lo = rank(lo,);
+
+        self.entries(lo..rank(hi,).max(lo,),)
+    }
+
+    // Entries in key order
+    pub fn iter(&self,) -> impl Iterator<Item = (K, &V,),> {
+        self.entries(0..self.n,)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::RngExt;
+
+    use super::*;
+
+    const CONFIGS: [(Base, Payload,); 4] = [
+        (Base::Eytzinger, Payload::Interleaved,),
+        (Base::Eytzinger, Payload::Separate,),
+        (Base::STree, Payload::Interleaved,),
+        (Base::STree, Payload::Separate,),
+    ];
+
+    #[test]
+    fn test_get_basic() {
+        for (kind, payload,) in CONFIGS {
+            let mut map = StaticMap::new(
+                &[10, 20, 30, i32::MAX,],
+                &["a", "b", "c", "d",],
+                kind,
+                payload,
+            );
+
+            assert_eq!(map.len(), 4);
+            assert_eq!(map.get(&20,), Some(&"b",));
+            assert_eq!(map.get(&i32::MAX,), Some(&"d",));
+            assert_eq!(map.get(&25,), None);
+            assert_eq!(map.get(&5,), None);
+
+            *map.get_mut(&30,).unwrap() = "e";
+            assert_eq!(map.get(&30,), Some(&"e",));
+            assert!(map.get_mut(&31,).is_none());
+
+            let range: Vec<_,> = map.range(&15, &31,).collect();
+            assert_eq!(range, vec![(20, &"b",), (30, &"e",)]);
+            assert_eq!(map.range(&31, &15,).count(), 0);
+            assert_eq!(map.iter().count(), 4);
+        }
+    }
+
+    #[test]
+    fn test_empty() {
+        for (kind, payload,) in CONFIGS {
+            let map = StaticMap::<i32, u8,>::new(&[], &[], kind, payload,);
+
+            assert!(map.is_empty());
+            assert_eq!(map.get(&0,), None);
+            assert_eq!(map.iter().count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [1, 15, 16, 17, 300, 5000,] {
+            let truth: BTreeMap<i32, u64,> =
+                (0..n).map(|_| (rng.random_range(0..20_000,), rng.random(),),).collect();
+            let keys: Vec<i32,> = truth.keys().copied().collect();
+            let values: Vec<u64,> = truth.values().copied().collect();
+
+            for (kind, payload,) in CONFIGS {
+                let mut map = StaticMap::new(&keys, &values, kind, payload,);
+                let mut truth = truth.clone();
+
+                assert!(
+                    map.iter()
+                        .map(|(k, &v,)| (k, v,),)
+                        .eq(truth.iter().map(|(&k, &v,)| (k, v,),))
+                );
+
+                for _ in 0..500 {
+                    let key = rng.random_range(-10..20_010,);
+                    assert_eq!(
+                        map.get(&key,),
+                        truth.get(&key,),
+                        "{:?} {:?}",
+                        kind,
+                        payload
+                    );
+
+                    if let Some(value,) = map.get_mut(&key,) {
+                        *value += 1;
+                        *truth.get_mut(&key,).unwrap() += 1;
+                    }
+
+                    let hi = key + rng.random_range(0..500,);
+                    assert!(
+                        map.range(&key, &hi,)
+                            .map(|(k, &v,)| (k, v,),)
+                            .eq(truth.range(key..hi,).map(|(&k, &v,)| (k, v,),)),
+                        "{:?} {:?}",
+                        kind,
+                        payload
+                    );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,261 @@
+use std::{
+    fs,
+    ops::{Deref, DerefMut},
+};
+
+
+// Transparent huge pages are 2MB on x86_64 and on aarch64 with 4KB base pages
+const HUGE_PAGE: usize = 2 << 20;
+
+
+// Pages backing a layout buffer
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pages {
+    // Base pages only, the kernel is told not to use transparent huge pages
+    Base,
+    // Anonymous memory aligned on huge pages, with madvise(MADV_HUGEPAGE)
+    Transparent,
+    // Pages reserved in the hugetlbfs pool, with MAP_HUGETLB
+    HugeTlb,
+}
+
+// Buffer of len elements in its own anonymous mapping. A request for huge pages falls
+// back to the next mode when the kernel refuses it: HugeTlb to Transparent to Base.
+pub struct PageBuffer<T: Copy,> {
+    ptr:   *mut T,
+    len:   usize,
+    bytes: usize,
+    pages: Pages,
+}
+
+
+fn base_page() -> usize {
+    unsafe { libc::sysconf(libc::_SC_PAGESIZE,) as usize }
+}
+
+// Default hugetlbfs page size, the one MAP_HUGETLB maps
+fn hugetlb_page() -> usize {
+    fs::read_to_string("/proc/meminfo",)
+        .ok()
+        .and_then(|meminfo| {
+            let line = meminfo.lines().find(|line| line.starts_with("Hugepagesize:",),)?;
+            line.split_whitespace().nth(1,)?.parse::<usize>().ok()
+        },)
+        .map_or(HUGE_PAGE, |kb| kb << 10,)
+}
+
+fn map(bytes: usize, flags: libc::c_int,) -> Option<*mut u8,> {
+    let ptr = unsafe {
+        libc::mmap(
+            std::ptr::null_mut(),
+            bytes,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS | flags,
+            -1,
+            0,
+        )
+    };
+
+    (ptr != libc::MAP_FAILED).then_some(ptr as *mut u8,)
+}
+
+fn unmap(ptr: *mut u8, bytes: usize,) {
+    unsafe {
+        libc::munmap(ptr as *mut libc::c_void, bytes,);
+    }
+}
+
+// Maps at least size bytes with the requested pages, or the closest ones the kernel
+// gives. Returns the mapping, its length and the pages it was mapped with.
+fn allocate(size: usize, pages: Pages,) -> (*mut u8, usize, Pages,) {
+    let size = size.max(1,);
+
+    #[cfg(target_os = "linux")]
+    if pages == Pages::HugeTlb {
+        let bytes = size.next_multiple_of(hugetlb_page(),);
+        if let Some(ptr,) = map(bytes, libc::MAP_HUGETLB,) {
+            return (ptr, bytes, Pages::HugeTlb,);
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    if pages != Pages::Base {
+        // Over map by one huge page, then trim both ends to align the mapping. Without
+        // room for the extra page, the buffer may still fit in base pages.
+        let bytes = size.next_multiple_of(HUGE_PAGE,);
+        if let Some(raw,) = map(bytes + HUGE_PAGE, 0,) {
+            let head = raw.addr().next_multiple_of(HUGE_PAGE,) - raw.addr();
+            let ptr = unsafe { raw.add(head,) };
+
+            if head > 0 {
+                unmap(raw, head,);
+            }
+            unmap(unsafe { ptr.add(bytes,) }, HUGE_PAGE - head,);
+
+            let advised = unsafe {
+                libc::madvise(ptr as *mut libc::c_void, bytes, libc::MADV_HUGEPAGE,)
+            };
+            if advised == 0 {
+                return (ptr, bytes, Pages::Transparent,);
+            }
+            unmap(ptr, bytes,);
+        }
+    }
+
+    let bytes = size.next_multiple_of(base_page(),);
+    let ptr = map(bytes, 0,).expect("cannot map the buffer",);
+
+    // With transparent huge pages set to always, base pages must be asked for
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::madvise(ptr as *mut libc::c_void, bytes, libc::MADV_NOHUGEPAGE,);
+    }
+
+    (ptr, bytes, Pages::Base,)
+}
+
+
+impl<T: Copy,> PageBuffer<T,> {
+    // Buffer holding f(0), f(1), ..., f(len - 1)
+    #[must_use]
+    pub fn from_fn(pages: Pages, len: usize, mut f: impl FnMut(usize,) -> T,) -> Self {
+        // Like any allocation at most isize::MAX bytes, which leaves room to round the
+        // size up to whole pages
+        let size = len
+            .checked_mul(size_of::<T,>(),)
+            .filter(|&size| size <= isize::MAX as usize,)
+            .expect("buffer larger than the address space",);
+
+        let (ptr, bytes, pages,) = allocate(size, pages,);
+        let ptr = ptr as *mut T;
+
+        for i in 0..len {
+            unsafe { ptr.add(i,).write(f(i,),) };
+        }
+
+        Self { ptr, len, bytes, pages, }
+    }
+
+    // Pages the buffer was mapped with, after the fallbacks
+    #[must_use]
+    pub fn pages(&self,) -> Pages {
+        self.pages
+    }
+
+    // Size of the pages which actually back the buffer. Transparent huge pages are only
+    // a hint: the kernel reports in smaps how much of the mapping they back.
+    #[must_use]
+    pub fn page_size(&self,) -> usize {
+        match self.pages {
+            | Pages::Base => base_page(),
+            | Pages::HugeTlb => hugetlb_page(),
+            | Pages::Transparent => {
+                if self.anon_huge_bytes() > 0 {
+                    HUGE_PAGE
+                } else {
+                    base_page()
+                }
+            },
+        }
+    }
+
+    // AnonHugePages of the smaps entry holding the buffer
+    fn anon_huge_bytes(&self,) -> usize {
+        let Ok(smaps,) = fs::read_to_string("/proc/self/smaps",) else {
+            return 0;
+        };
+        let addr = self.ptr.addr();
+
+        let mut inside = false;
+        for line in smaps.lines() {
+            let mut fields = line.split_whitespace();
+            let Some(first,) = fields.next() else {
+                continue;
+            };
+
+            if let Some((start, end,),) = first.split_once('-',)
+                && let (Ok(start,), Ok(end,),) =
+                    (usize::from_str_radix(start, 16,), usize::from_str_radix(end, 16,),)
+            {
+                inside = start <= addr && addr < end;
+            } else if inside && first == "AnonHugePages:" {
+                return fields
+                    .next()
+                    .and_then(|kb| kb.parse::<usize>().ok(),)
+                    .unwrap_or(0,)
+                    << 10;
+            }
+        }
+
+        0
+    }
+}
+
+impl<T: Copy,> Deref for PageBuffer<T,> {
+    type Target = [T];
+
+    fn deref(&self,) -> &[T] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len,) }
+    }
+}
+
+impl<T: Copy,> DerefMut for PageBuffer<T,> {
+    fn deref_mut(&mut self,) -> &mut [T] {
+        unsafe { std::slice::from_raw_parts_mut(self.ptr, self.len,) }
+    }
+}
+
+impl<T: Copy,> Drop for PageBuffer<T,> {
+    fn drop(&mut self,) {
+        unmap(self.ptr as *mut u8, self.bytes,);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contents() {
+        for pages in [Pages::Base, Pages::Transparent, Pages::HugeTlb,] {
+            for len in [0, 1, 1000, 3 << 20,] {
+                let mut buffer = PageBuffer::from_fn(pages, len, |i| i as u32,);
+
+                assert_eq!(buffer.len(), len);
+                assert!(buffer.iter().enumerate().all(|(i, &x,)| x == i as u32),);
+
+                buffer.iter_mut().for_each(|x| *x += 1,);
+                assert!(buffer.iter().enumerate().all(|(i, &x,)| x == i as u32 + 1),);
+            }
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let base = PageBuffer::from_fn(Pages::Base, 1 << 20, |i| i,);
+        assert_eq!(base.pages(), Pages::Base);
+        assert_eq!(base.page_size(), base_page());
+
+        // Whatever the kernel grants, huge page buffers are aligned on their pages
+        for pages in [Pages::Transparent, Pages::HugeTlb,] {
+            let buffer = PageBuffer::from_fn(pages, 1 << 20, |i| i,);
+
+            match buffer.pages() {
+                | Pages::Base => assert_eq!(buffer.page_size(), base_page()),
+                | Pages::Transparent => assert_eq!(buffer.ptr.addr() % HUGE_PAGE, 0),
+                | Pages::HugeTlb => {
+                    assert_eq!(buffer.ptr.addr() % hugetlb_page(), 0);
+                    assert_eq!(buffer.page_size(), hugetlb_page());
+                },
+            }
+            assert!(buffer.page_size() >= base_page());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "larger than the address space")]
+    fn test_size_overflow() {
+        let _ = PageBuffer::from_fn(Pages::Base, usize::MAX / 4, |i| i as u64,);
+    }
+}
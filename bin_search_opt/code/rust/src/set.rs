@@ -0,0 +1,399 @@
+use crate::{
+    backend::{Backend, Isa, dispatch},
+    eytzinger::{self, Prefetch, create_layout_eytzinger},
+    stree::{self, Kernels, Key, create_layout_stree},
+    updatable::Base,
+};
+
+const BLOCK: usize = 16;
+// Queries of contains_many searched by one call of the batch searches
+const CHUNK: usize = 256;
+
+
+// Set of distinct keys laid out by a static layout, searched with the kernels of the
+// backend current when it is built
+pub struct StaticSet<K: Key,> {
+    kind:    Base,
+    backend: Backend,
+    n:       usize,
+    data:    Vec<K,>,
+}
+
+
+impl<K: Key,> StaticSet<K,> {
+    // The keys are sorted and deduplicated, they must all be comparable
+    #[must_use]
+    pub fn new(keys: impl IntoIterator<Item = K,>, kind: Base,) -> Self {
+        let mut keys: Vec<K,> = keys.into_iter().collect();
+        keys.sort_unstable_by(|a, b| {
+            a.partial_cmp(b,).expect("set keys must be comparable",)
+        },);
+        keys.dedup();
+
+        let data = match kind {
+            | Base::Eytzinger => create_layout_eytzinger(&keys,),
+            | Base::STree => create_layout_stree::<K, BLOCK,>(&keys,),
+        };
+
+        Self { kind, backend: Backend::current(), n: keys.len(), data, }
+    }
+
+    // Same set searched with the kernels of backend
+    #[must_use]
+    pub fn with_backend(self, backend: Backend,) -> Self {
+        assert!(backend.is_supported(), "{:?} is not supported", backend);
+
+        Self { backend, ..self }
+    }
+
+    #[must_use]
+    pub fn backend(&self,) -> Backend {
+        self.backend
+    }
+
+    #[must_use]
+    pub fn len(&self,) -> usize {
+        self.n
+    }
+
+    #[cfg_attr(not(test), expect(unused))]
+    #[must_use]
+    pub fn is_empty(&self,) -> bool {
+        self.n == 0
+    }
+
+    // Unlike a lower bound, a membership test is over as soon as a node holds the key
+    #[must_use]
+    #[inline(always)]
+    pub fn contains(&self, key: &K,) -> bool {
+        let t = *key;
+
+        match self.kind {
+            | Base::Eytzinger => Prefetch::of(self.backend,).contains(&self.data, &t,),
+            | Base::STree => dispatch!(self.backend, I => self.stree_contains::<I,>(t,)),
+        }
+    }
+
+    #[inline(always)]
+    fn stree_contains<I: Isa,>(&self, t: K,) -> bool
+    where
+        K: Kernels<I,>,
+    {
+        let base_ptr = self.data.as_ptr();
+        let mut cur = 0;
+
+        while cur * BLOCK < self.n {
+            let lookahead = (cur * (BLOCK + 1) + I::prefetch_offset(BLOCK,)) * BLOCK;
+            if lookahead < self.n {
+                unsafe { I::prefetch(base_ptr.add(lookahead,) as *const i8,) };
+            }
+
+            let ptr = unsafe { base_ptr.add(cur * BLOCK,) };
+            let i = unsafe { <K as Kernels<I,>>::node_lower::<BLOCK,>(ptr, t,) };
+
+            // The padding is told apart by its position
+            if i < BLOCK && cur * BLOCK + i < self.n && unsafe { *ptr.add(i,) } == t {
+                return true;
+            }
+
+            cur = cur * (BLOCK + 1) + i + 1;
+        }
+
+        false
+    }
+
+    // Membership of every target, from the batch searches of the layout
+    pub fn contains_many(&self, targets: &[K], results: &mut [bool],) {
+        assert_eq!(targets.len(), results.len());
+
+        let mut positions = [None; CHUNK];
+
+        for (targets, results,) in targets.chunks(CHUNK,).zip(results.chunks_mut(CHUNK,),)
+        {
+            let positions = &mut positions[..targets.len()];
+
+            match self.kind {
+                | Base::Eytzinger => {
+                    eytzinger::lower_bound_batch_with::<_, _, 16,>(
+                        self.backend,
+                        &self.data,
+                        targets,
+                        positions,
+                    );
+                },
+                | Base::STree => {
+                    stree::lower_bound_batch_with::<K, BLOCK, 16,>(
+                        self.backend,
+                        &self.data,
+                        targets,
+                        positions,
+                    );
+                },
+            }
+
+            for ((result, target,), position,) in
+                results.iter_mut().zip(targets,).zip(positions,)
+            {
+                // The S-tree searches skip a key equal to the padding, checked apart
+                *result = match position {
+                    | Some(pos,) => self.data[*pos] == *target,
+                    | None => *target == K::PADDING && self.contains(target,),
+                };
+            }
+        }
+    }
+
+    #[inline(always)]
+    fn key(&self, rank: usize,) -> K {
+        let idx = match self.kind {
+            | Base::Eytzinger => eytzinger::rank_to_index(self.n, rank,),
+            | Base::STree => stree::rank_to_index::<BLOCK,>(self.n, rank,),
+        };
+
+        self.data[idx]
+    }
+
+    // First rank from from on whose key is not less than target: the rank is found by
+    // doubling steps, then by a binary search in the last step, each probe going
+    // through the layout.
+    fn gallop(&self, from: usize, target: K,) -> usize {
+        let mut lo = from;
+        let mut hi = from;
+        let mut step = 1;
+
+        while hi < self.n && self.key(hi,) < target {
+            lo = hi + 1;
+            hi = from + step;
+            step *= 2;
+        }
+
+        let mut hi = hi.min(self.n,);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if self.key(mid,) < target {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+
+        lo
+    }
+
+    // Keys in both sets, the smaller one is walked while galloping through the other
+    pub fn intersection<'a,>(
+        &'a self, other: &'a Self,
+    ) -> impl Iterator<Item = K,> + 'a {
+        let (small, large,) =
+            if self.n <= other.n { (self, other,) } else { (other, self,) };
+        let mut j = 0;
+
+        (0..small.n).filter_map(move |i| {
+            let key = small.key(i,);
+            j = large.gallop(j, key,);
+
+            (j < large.n && large.key(j,) == key).then_some(key,)
+        },)
+    }
+
+    // Keys of self which are not in other
+    pub fn difference<'a,>(&'a self, other: &'a Self,) -> impl Iterator<Item = K,> + 'a {
+        let mut j = 0;
+
+        (0..self.n).filter_map(move |i| {
+            let key = self.key(i,);
+            j = other.gallop(j, key,);
+
+            (j == other.n || other.key(j,) != key).then_some(key,)
+        },)
+    }
+
+    // Keys in either set. Each side emits the run of its keys less than the next key of
+    // the other side, found by galloping.
+    pub fn union<'a,>(&'a self, other: &'a Self,) -> impl Iterator<Item = K,> + 'a {
+        let (mut i, mut j,) = (0, 0,);
+        // Ranks of the run being emitted, and whether it is a run of self
+        let (mut run, mut from_self,) = (0..0, true,);
+
+        std::iter::from_fn(move || {
+            if let Some(rank,) = run.next() {
+                return Some(if from_self { self.key(rank,) } else { other.key(rank,) },);
+            }
+
+            match (i < self.n, j < other.n,) {
+                | (false, false,) => None,
+                | (true, false,) => {
+                    i += 1;
+                    Some(self.key(i - 1,),)
+                },
+                | (false, true,) => {
+                    j += 1;
+                    Some(other.key(j - 1,),)
+                },
+                | (true, true,) => {
+                    let (a, b,) = (self.key(i,), other.key(j,),);
+
+                    if a == b {
+                        (i, j,) = (i + 1, j + 1,);
+                        return Some(a,);
+                    }
+
+                    if a < b {
+                        let end = self.gallop(i, b,);
+                        (run, from_self,) = (i + 1..end, true,);
+                        i = end;
+                        Some(a,)
+                    } else {
+                        let end = other.gallop(j, a,);
+                        (run, from_self,) = (j + 1..end, false,);
+                        j = end;
+                        Some(b,)
+                    }
+                },
+            }
+        },)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn test_contains_basic() {
+        for kind in [Base::Eytzinger, Base::STree,] {
+            let set = StaticSet::new([30, 10, 20, 10, i32::MAX, 30,], kind,);
+
+            assert_eq!(set.len(), 4);
+            assert!(set.contains(&10,));
+            assert!(set.contains(&i32::MAX,));
+            assert!(!set.contains(&15,));
+            assert!(!set.contains(&i32::MIN,));
+
+            let targets = [i32::MAX, 5, 20, 21,];
+            let mut results = [false; 4];
+            set.contains_many(&targets, &mut results,);
+            assert_eq!(results, [true, false, true, false]);
+
+            let empty = StaticSet::new(std::iter::empty::<i32,>(), kind,);
+            assert!(empty.is_empty());
+            assert!(!empty.contains(&0,));
+            assert_eq!(empty.union(&set,).count(), 4);
+            assert_eq!(set.intersection(&empty,).count(), 0);
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        let mut rng = rand::rng();
+
+        let data: Vec<u16,> = (0..3000).map(|_| rng.random_range(0..5000,),).collect();
+        let truth: BTreeSet<u16,> = data.iter().copied().collect();
+        let targets: Vec<u16,> = (0..1000).map(|_| rng.random_range(0..5100,),).collect();
+
+        assert_eq!(
+            StaticSet::new(data.clone(), Base::STree,).backend(),
+            Backend::current()
+        );
+
+        for kind in [Base::Eytzinger, Base::STree,] {
+            for backend in Backend::supported() {
+                let set = StaticSet::new(data.clone(), kind,).with_backend(backend,);
+                assert_eq!(set.backend(), backend);
+
+                let mut results = vec![false; targets.len()];
+                set.contains_many(&targets, &mut results,);
+
+                for (target, result,) in targets.iter().zip(&results,) {
+                    assert_eq!(
+                        set.contains(target,),
+                        truth.contains(target,),
+                        "{:?} {:?}",
+                        kind,
+                        backend
+                    );
+                    assert_eq!(
+                        *result,
+                        truth.contains(target,),
+                        "{:?} {:?}",
+                        kind,
+                        backend
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "is not supported")]
+    fn test_unsupported_backend() {
+        let unsupported = [Backend::Neon, Backend::Sse41,]
+            .into_iter()
+            .find(|backend| !backend.is_supported(),)
+            .unwrap();
+        let _ = StaticSet::new([1, 2, 3,], Base::STree,).with_backend(unsupported,);
+    }
+
+    #[test]
+    fn test_fuzzy() {
+        let mut rng = rand::rng();
+
+        for kind in [Base::Eytzinger, Base::STree,] {
+            for (n, m, range,) in [
+                (100, 100, 300,),
+                (1000, 20, 5000,),
+                (5, 3000, 4000,),
+                (2000, 2000, 2000,),
+            ] {
+                let a: Vec<i64,> = (0..n).map(|_| rng.random_range(0..range,),).collect();
+                let b: Vec<i64,> = (0..m).map(|_| rng.random_range(0..range,),).collect();
+
+                let (set_a, set_b,) =
+                    (StaticSet::new(a.clone(), kind,), StaticSet::new(b.clone(), kind,),);
+                let (truth_a, truth_b,): (BTreeSet<i64,>, BTreeSet<i64,>,) =
+                    (a.into_iter().collect(), b.into_iter().collect(),);
+
+                assert_eq!(set_a.len(), truth_a.len());
+
+                let targets: Vec<i64,> =
+                    (0..1000).map(|_| rng.random_range(-5..range + 5,),).collect();
+                let mut results = vec![false; targets.len()];
+                set_a.contains_many(&targets, &mut results,);
+
+                for (target, result,) in targets.iter().zip(&results,) {
+                    assert_eq!(
+                        set_a.contains(target,),
+                        truth_a.contains(target,),
+                        "{:?}",
+                        kind
+                    );
+                    assert_eq!(*result, truth_a.contains(target,), "{:?}", kind);
+                }
+
+                assert!(
+                    set_a
+                        .intersection(&set_b,)
+                        .eq(truth_a.intersection(&truth_b,).copied())
+                );
+                assert!(
+                    set_b
+                        .intersection(&set_a,)
+                        .eq(truth_b.intersection(&truth_a,).copied())
+                );
+                assert!(
+                    set_a.difference(&set_b,).eq(truth_a.difference(&truth_b,).copied())
+                );
+                assert!(
+                    set_b.difference(&set_a,).eq(truth_b.difference(&truth_a,).copied())
+                );
+                assert!(set_a.union(&set_b,).eq(truth_a.union(&truth_b,).copied()));
+                assert!(set_b.union(&set_a,).eq(truth_b.union(&truth_a,).copied()));
+            }
+        }
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    backend::{dispatch, Backend, Isa},
+    stree::{Key, Kernels},
+};
+
+const MAX_HEIGHT: usize = 32;
+
+
+// The layout is stored leaves first: layer 0 is the sorted data padded to a multiple
+// of the block size, and each following layer indexes the one below it, up to the
+// root. Returns the offset of every layer and the number of layers.
+#[inline(always)]
+fn layer_offsets(n: usize, block: usize,) -> ([usize; MAX_HEIGHT], usize,) {
+    let mut offsets = [0; MAX_HEIGHT];
+    let mut height = 1;
+    let mut blocks = n.div_ceil(block,);
+
+    while blocks > 1 {
+        offsets[height] = offsets[height - 1] + blocks * block;
+        blocks = blocks.div_ceil(block + 1,);
+        height += 1;
+    }
+    offsets[height] = offsets[height - 1] + blocks * block;
+
+    (offsets, height,)
+}
+
+// Shape of the layout of n keys, computed once along with it, so that the searches do
+// not walk the layers again for every query.
+#[derive(Clone, Copy, Debug)]
+pub struct Layers {
+    n:       usize,
+    block:   usize,
+    height:  usize,
+    offsets: [usize; MAX_HEIGHT],
+}
+
+impl Layers {
+    #[must_use]
+    pub fn new(n: usize, block: usize,) -> Self {
+        let (offsets, height,) = layer_offsets(n, block,);
+        Self { n, block, height, offsets, }
+    }
+}
+
+
+#[must_use]
+pub fn create_layout_splus<K: Key, const B: usize,>(data: &[K],) -> Vec<K,> {
+    const { assert!(B.is_power_of_two() && B >= 4 && B <= 64) };
+
+    let n = data.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let Layers { offsets, height, .. } = Layers::new(n, B,);
+    let mut splus = vec![K::PADDING; offsets[height]];
+
+    splus[..n].copy_from_slice(data,);
+
+    for h in 1..height {
+        // Each key is the smallest key of the subtree on its right, which is the first
+        // key of the leftmost leaf below it.
+        let leaves_per_child = (B + 1).pow(h as u32 - 1,);
+
+        for i in 0..offsets[h + 1] - offsets[h] {
+            let (node, slot,) = (i / B, i % B,);
+            let child = node * (B + 1) + slot + 1;
+            let first = child * leaves_per_child * B;
+
+            if first < n {
+                splus[offsets[h] + i] = data[first];
+            }
+        }
+    }
+
+    splus
+}
+
+
+// The searches read whole nodes at the offsets of the layers, which must be those of
+// this layout.
+#[inline(always)]
+fn check_layers<K, const B: usize,>(data: &[K], layers: &Layers,) {
+    assert!(
+        layers.block == B && layers.offsets[layers.height] == data.len(),
+        "layers of {} keys in blocks of {} do not match this layout",
+        layers.n,
+        layers.block
+    );
+}
+
+// As for the S-tree, the _with variants run the kernels of the given backend
+macro_rules! impl_bound_splus {
+    (
+        $(#[$attr:meta])* $name:ident,
+        $name_with:ident,
+        $simd_intrinsic:ident,
+        $inclusive:literal
+    ) => {
+        $(#[$attr])*
+        pub fn $name<K: Key, const B: usize,>(
+            data: &[K],
+            layers: &Layers,
+            target: &K,
+        ) -> usize {
+            $name_with::<K, B,>(Backend::current(), data, layers, target,)
+        }
+
+        pub fn $name_with<K: Key, const B: usize,>(
+            backend: Backend,
+            data: &[K],
+            layers: &Layers,
+            target: &K,
+        ) -> usize {
+            check_layers::<K, B,>(data, layers,);
+
+            #[inline(always)]
+            fn search<I: Isa, K: Key + Kernels<I,>, const B: usize,>(
+                data: &[K],
+                layers: &Layers,
+                target: &K,
+            ) -> usize {
+                let base_ptr = data.as_ptr();
+                let (n, height, offsets,) = (layers.n, layers.height, &layers.offsets,);
+                if n == 0 {
+                    return 0;
+                }
+
+                let t = *target;
+
+                // The padding keys of missing children would count as not greater than
+                // the padding value itself, and send the search out of the layout.
+                if $inclusive && t == K::PADDING {
+                    return n;
+                }
+
+                let mut k = 0;
+                for h in (1..height).rev() {
+                    let ptr = unsafe { base_ptr.add(offsets[h] + k,) };
+                    let i = unsafe { <K as Kernels<I,>>::$simd_intrinsic::<B,>(ptr, t,) };
+
+                    k = k * (B + 1) + i * B;
+                }
+
+                let ptr = unsafe { base_ptr.add(k,) };
+                let i = unsafe { <K as Kernels<I,>>::$simd_intrinsic::<B,>(ptr, t,) };
+
+                std::cmp::min(k + i, n,)
+            }
+
+            dispatch!(backend, I => search::<I, K, B,>(data, layers, target,))
+        }
+    };
+}
+
+impl_bound_splus!(lower_bound, lower_bound_with, node_lower, false);
+impl_bound_splus!(upper_bound, upper_bound_with, node_upper, true);
+
+
+#[must_use]
+pub fn equal_range<K: Key, const B: usize,>(
+    data: &[K],
+    layers: &Layers,
+    target: &K,
+) -> (usize, usize,) {
+    (
+        lower_bound::<K, B,>(data, layers, target,),
+        upper_bound::<K, B,>(data, layers, target,),
+    )
+}
+
+#[must_use]
+pub fn count<K: Key, const B: usize,>(data: &[K], layers: &Layers, target: &K,) -> usize {
+    let (lower, upper,) = equal_range::<K, B,>(data, layers, target,);
+    upper - lower
+}
+
+// Number of keys in the half-open range [lo, hi)
+#[must_use]
+pub fn count_in_range<K: Key, const B: usize,>(
+    data: &[K],
+    layers: &Layers,
+    lo: &K,
+    hi: &K,
+) -> usize {
+    lower_bound::<K, B,>(data, layers, hi,)
+        .saturating_sub(lower_bound::<K, B,>(data, layers, lo,),)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn test_splus_layout_structure() {
+        let input: Vec<i32,> = (0..40).collect();
+        let layout = create_layout_splus::<_, 16,>(&input,);
+
+        // Leaves: the sorted data padded to 3 blocks
+        assert_eq!(&layout[..40], &input[..]);
+        assert_eq!(&layout[40..48], &[i32::MAX; 8]);
+
+        // Root: the first key of the 2nd and 3rd leaves
+        assert_eq!(layout.len(), 64);
+        assert_eq!(&layout[48..50], &[16, 32]);
+        assert_eq!(&layout[50..], &[i32::MAX; 14]);
+    }
+
+    #[test]
+    fn test_splus_layout_heights() {
+        // One leaf, then one root over 17 leaves, then two levels of internal nodes
+        assert_eq!(layer_offsets(16, 16,).1, 1);
+        assert_eq!(layer_offsets(17, 16,).1, 2);
+        assert_eq!(layer_offsets(16 * 17, 16,).1, 2);
+        assert_eq!(layer_offsets(16 * 17 + 1, 16,).1, 3);
+    }
+
+    #[test]
+    fn test_lower_bound_basic() {
+        let data = vec![10, 20, 20, 20, 30];
+        let layout = create_layout_splus::<_, 16,>(&data,);
+        let layers = Layers::new(data.len(), 16,);
+
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &5,), 0);
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &10,), 0);
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &20,), 1);
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &25,), 4);
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &40,), 5);
+    }
+
+    #[test]
+    fn test_upper_bound_basic() {
+        let data = vec![10, 20, 20, 20, 30];
+        let layout = create_layout_splus::<_, 16,>(&data,);
+        let layers = Layers::new(data.len(), 16,);
+
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &5,), 0);
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &10,), 1);
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &20,), 4);
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &30,), 5);
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &100,), 5);
+    }
+
+    #[test]
+    fn test_bound_padding_value() {
+        let data = vec![1, 5, i32::MAX, i32::MAX];
+        let layout = create_layout_splus::<_, 16,>(&data,);
+        let layers = Layers::new(data.len(), 16,);
+
+        assert_eq!(lower_bound::<_, 16,>(&layout, &layers, &i32::MAX,), 2);
+        assert_eq!(upper_bound::<_, 16,>(&layout, &layers, &i32::MAX,), 4);
+    }
+
+    #[test]
+    #[should_panic(expected = "do not match")]
+    fn test_bound_layers_mismatch() {
+        // Layers of a larger layout would send the search past the end of this one
+        let data: Vec<i32,> = (0..40).collect();
+        let layout = create_layout_splus::<_, 16,>(&data,);
+        let layers = Layers::new(400, 16,);
+
+        let _ = lower_bound::<_, 16,>(&layout, &layers, &7,);
+    }
+
+    fn check_width<const B: usize,>() {
+        let mut rng = rand::rng();
+
+        let sizes = [1, 15, 16, 17, 300, 2000, B * (B + 1) * (B + 1) + 5,];
+        for n in sizes {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..(n as i32 / 20 + 100),),).collect();
+            data.sort_unstable();
+
+            let layout = create_layout_splus::<_, B,>(&data,);
+            let layers = Layers::new(data.len(), B,);
+
+            for _ in 0..1000 {
+                let target = rng.random_range(0..(n as i32 / 20 + 110),);
+
+                for backend in Backend::supported() {
+                    assert_eq!(
+                        lower_bound_with::<_, B,>(backend, &layout, &layers, &target,),
+                        data.partition_point(|&x| x < target,),
+                        "Lower bound mismatch for target {}, B = {}, {:?}",
+                        target,
+                        B,
+                        backend
+                    );
+                    assert_eq!(
+                        upper_bound_with::<_, B,>(backend, &layout, &layers, &target,),
+                        data.partition_point(|&x| x <= target,),
+                        "Upper bound mismatch for target {}, B = {}, {:?}",
+                        target,
+                        B,
+                        backend
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_fuzzy() {
+        check_width::<4,>();
+        check_width::<8,>();
+        check_width::<16,>();
+        check_width::<32,>();
+        check_width::<64,>();
+    }
+
+    #[test]
+    fn test_bound_key_types() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<u64,> = (0..5000).map(|_| rng.random(),).collect();
+        data.extend([0, u64::MAX, u64::MAX,],);
+        data.sort_unstable();
+        let layout = create_layout_splus::<_, 8,>(&data,);
+        let layers = Layers::new(data.len(), 8,);
+
+        for target in data.iter().step_by(7,).chain(&[1, u64::MAX - 1,],) {
+            assert_eq!(
+                lower_bound::<_, 8,>(&layout, &layers, target,),
+                data.partition_point(|x| x < target,)
+            );
+            assert_eq!(
+                upper_bound::<_, 8,>(&layout, &layers, target,),
+                data.partition_point(|x| x <= target,)
+            );
+        }
+
+        let mut data: Vec<f64,> =
+            (0..5000).map(|_| rng.random_range(-1e9..1e9,),).collect();
+        data.sort_unstable_by(|a, b| a.partial_cmp(b,).unwrap(),);
+        let layout = create_layout_splus::<_, 8,>(&data,);
+        let layers = Layers::new(data.len(), 8,);
+
+        for _ in 0..1000 {
+            let target = rng.random_range(-1.1e9..1.1e9,);
+            assert_eq!(
+                lower_bound::<_, 8,>(&layout, &layers, &target,),
+                data.partition_point(|&x| x < target,)
+            );
+            assert_eq!(
+                upper_bound::<_, 8,>(&layout, &layers, &target,),
+                data.partition_point(|&x| x <= target,)
+            );
+        }
+    }
+
+    #[test]
+    fn test_count_fuzzy() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> = (0..2000).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+        let layout = create_layout_splus::<_, 16,>(&data,);
+        let layers = Layers::new(data.len(), 16,);
+
+        for target in -2..102 {
+            assert_eq!(
+                equal_range::<_, 16,>(&layout, &layers, &target,),
+                (
+                    data.partition_point(|&x| x < target,),
+                    data.partition_point(|&x| x <= target,),
+                )
+            );
+            assert_eq!(
+                count::<_, 16,>(&layout, &layers, &target,),
+                data.iter().filter(|&&x| x == target,).count()
+            );
+        }
+
+        for _ in 0..1000 {
+            let (lo, hi,) = (rng.random_range(-2..102,), rng.random_range(-2..102,),);
+            let expected = data.iter().filter(|&&x| lo <= x && x < hi,).count();
+            assert_eq!(count_in_range::<_, 16,>(&layout, &layers, &lo, &hi,), expected);
+        }
+    }
+}
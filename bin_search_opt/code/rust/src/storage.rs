@@ -0,0 +1,495 @@
+use std::{
+    fs::File,
+    io::{self, Write},
+    marker::PhantomData,
+    os::fd::AsRawFd,
+    path::Path,
+};
+
+use crate::stree::Key;
+
+
+const MAGIC: [u8; 8] = *b"BSLAYOUT";
+const VERSION: u32 = 1;
+// Written in native order, it reads back differently on a machine of the other order
+const ENDIANNESS: u32 = 0x0102_0304;
+// The keys start at a 64 bytes boundary of the page aligned mapping
+const HEADER_SIZE: usize = 64;
+
+
+// Layout of the keys stored in an index file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layout {
+    Eytzinger,
+    STree,
+}
+
+// Key types which can be stored, each with its code in the header
+pub trait Stored: Key {
+    const TYPE: u8;
+}
+
+macro_rules! impl_stored {
+    ($($t:ty => $code:expr),* $(,)?) => {
+        $(
+            impl Stored for $t {
+                const TYPE: u8 = $code;
+            }
+        )*
+    };
+}
+
+impl_stored!(
+    i8 => 1, u8 => 2, i16 => 3, u16 => 4, i32 => 5, u32 => 6, i64 => 7, u64 => 8,
+    f32 => 9, f64 => 10,
+);
+
+
+// Header of an index file, all fields in native order:
+//
+//   0  magic       8 bytes
+//   8  version     u32
+//  12  endianness  u32
+//  16  block       u32, the keys per node of an S-tree, 1 for an Eytzinger layout
+//  20  layout      u8
+//  21  key type    u8
+//  22  key size    u8
+//  24  count       u64, the number of keys
+//  32  padding     u64, the number of padding keys after them
+//  40  checksum    u64, of the header with this field zeroed, then of the stored keys
+//
+// and zeros up to HEADER_SIZE.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Header {
+    layout:   Layout,
+    key_type: u8,
+    key_size: u8,
+    block:    u32,
+    count:    u64,
+    padding:  u64,
+    checksum: u64,
+}
+
+impl Header {
+    fn encode(&self,) -> [u8; HEADER_SIZE] {
+        let mut bytes = [0; HEADER_SIZE];
+
+        bytes[0..8].copy_from_slice(&MAGIC,);
+        bytes[8..12].copy_from_slice(&VERSION.to_ne_bytes(),);
+        bytes[12..16].copy_from_slice(&ENDIANNESS.to_ne_bytes(),);
+        bytes[16..20].copy_from_slice(&self.block.to_ne_bytes(),);
+        bytes[20] = self.layout as u8;
+        bytes[21] = self.key_type;
+        bytes[22] = self.key_size;
+        bytes[24..32].copy_from_slice(&self.count.to_ne_bytes(),);
+        bytes[32..40].copy_from_slice(&self.padding.to_ne_bytes(),);
+        bytes[40..48].copy_from_slice(&self.checksum.to_ne_bytes(),);
+
+        bytes
+    }
+
+    fn decode(bytes: &[u8],) -> io::Result<Self,> {
+        if bytes.len() < HEADER_SIZE {
+            return Err(invalid("file shorter than the header",),);
+        }
+
+        let u32_at = |i: usize| u32::from_ne_bytes(bytes[i..i + 4].try_into().unwrap(),);
+        let u64_at = |i: usize| u64::from_ne_bytes(bytes[i..i + 8].try_into().unwrap(),);
+
+        if bytes[0..8] != MAGIC {
+            return Err(invalid("not an index file",),);
+        }
+        if u32_at(12,) != ENDIANNESS {
+            return Err(invalid("index file written with the other endianness",),);
+        }
+        if u32_at(8,) != VERSION {
+            return Err(invalid("unsupported index file version",),);
+        }
+
+        let layout = match bytes[20] {
+            | 0 => Layout::Eytzinger,
+            | 1 => Layout::STree,
+            | _ => return Err(invalid("unknown layout",),),
+        };
+
+        Ok(Self {
+            layout,
+            key_type: bytes[21],
+            key_size: bytes[22],
+            block: u32_at(16,),
+            count: u64_at(24,),
+            padding: u64_at(32,),
+            checksum: u64_at(40,),
+        },)
+    }
+}
+
+fn invalid(message: &str,) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message,)
+}
+
+// FNV-1a over 8 bytes words, then over the remaining bytes
+fn fnv(mut hash: u64, bytes: &[u8],) -> u64 {
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    let (words, tail,) = bytes.as_chunks::<8>();
+
+    for word in words {
+        hash = (hash ^ u64::from_ne_bytes(*word,)).wrapping_mul(PRIME,);
+    }
+    for &byte in tail {
+        hash = (hash ^ byte as u64).wrapping_mul(PRIME,);
+    }
+
+    hash
+}
+
+// Covers the header too, so that a count or a padding which does not match the keys is
+// caught along with them.
+fn checksum(header: &[u8; HEADER_SIZE], keys: &[u8],) -> u64 {
+    let mut header = *header;
+    header[40..48].fill(0,);
+
+    fnv(fnv(0xCBF2_9CE4_8422_2325, &header,), keys,)
+}
+
+fn as_bytes<K: Stored,>(keys: &[K],) -> &[u8] {
+    // Every key type is a plain number without padding bytes
+    unsafe { std::slice::from_raw_parts(keys.as_ptr() as *const u8, size_of_val(keys,),) }
+}
+
+
+fn save<K: Stored,>(
+    path: &Path,
+    layout: Layout,
+    block: usize,
+    keys: &[K],
+    n: usize,
+) -> io::Result<(),> {
+    assert!(n <= keys.len(), "a layout of {} keys cannot hold {n} keys", keys.len());
+
+    let bytes = as_bytes(keys,);
+    let mut header = Header {
+        layout,
+        key_type: K::TYPE,
+        key_size: size_of::<K,>() as u8,
+        block: block as u32,
+        count: n as u64,
+        padding: (keys.len() - n) as u64,
+        checksum: 0,
+    };
+    header.checksum = checksum(&header.encode(), bytes,);
+
+    let mut file = File::create(path,)?;
+    file.write_all(&header.encode(),)?;
+    file.write_all(bytes,)?;
+    file.sync_all()
+}
+
+pub fn save_eytzinger<K: Stored,>(path: &Path, data: &[K],) -> io::Result<(),> {
+    save(path, Layout::Eytzinger, 1, data, data.len(),)
+}
+
+// data is the padded layout of n keys
+pub fn save_stree<K: Stored, const B: usize,>(
+    path: &Path,
+    data: &[K],
+    n: usize,
+) -> io::Result<(),> {
+    save(path, Layout::STree, B, data, n,)
+}
+
+
+// Read only mapping of a whole file, unmapped on drop
+struct Mapping {
+    ptr: *mut libc::c_void,
+    len: usize,
+}
+
+impl Mapping {
+    fn new(file: &File,) -> io::Result<Self,> {
+        let len = file.metadata()?.len() as usize;
+        if len < HEADER_SIZE {
+            return Err(invalid("file shorter than the header",),);
+        }
+
+        let ptr = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error(),);
+        }
+
+        Ok(Self { ptr, len, },)
+    }
+
+    fn bytes(&self,) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr as *const u8, self.len,) }
+    }
+}
+
+impl Drop for Mapping {
+    fn drop(&mut self,) {
+        unsafe {
+            libc::munmap(self.ptr, self.len,);
+        }
+    }
+}
+
+
+// Index file mapped in memory, whose keys are searched in place
+pub struct MappedLayout<K: Stored,> {
+    mapping: Mapping,
+    header:  Header,
+    _marker: PhantomData<K,>,
+}
+
+impl<K: Stored,> MappedLayout<K,> {
+    fn open(path: &Path, layout: Layout, block: usize,) -> io::Result<Self,> {
+        let mapping = Mapping::new(&File::open(path,)?,)?;
+        let bytes = mapping.bytes();
+        let header = Header::decode(bytes,)?;
+
+        if header.layout != layout {
+            return Err(invalid("index file of another layout",),);
+        }
+        if header.key_type != K::TYPE || header.key_size as usize != size_of::<K,>() {
+            return Err(invalid("index file of another key type",),);
+        }
+        if header.block as usize != block {
+            return Err(invalid("index file of another block size",),);
+        }
+
+        // The searches load whole nodes, which must all lie in the keys: an Eytzinger
+        // layout has no padding, an S-tree one only pads its last node.
+        if header.padding >= block as u64 {
+            return Err(invalid("index file padding longer than a node",),);
+        }
+        let keys = (header.count as usize).checked_add(header.padding as usize,);
+        if keys.is_some_and(|keys| keys % block != 0,) {
+            return Err(invalid("index file keys do not fill whole nodes",),);
+        }
+        let size = keys.and_then(|keys| keys.checked_mul(size_of::<K,>(),),);
+        if size != Some(bytes.len() - HEADER_SIZE,) {
+            return Err(invalid("index file size does not match its header",),);
+        }
+
+        let (head, keys,) = bytes.split_first_chunk::<HEADER_SIZE>().unwrap();
+        if checksum(head, keys,) != header.checksum {
+            return Err(invalid("index file checksum mismatch",),);
+        }
+
+        Ok(Self { mapping, header, _marker: PhantomData, },)
+    }
+
+    pub fn open_eytzinger(path: &Path,) -> io::Result<Self,> {
+        Self::open(path, Layout::Eytzinger, 1,)
+    }
+
+    pub fn open_stree<const B: usize,>(path: &Path,) -> io::Result<Self,> {
+        Self::open(path, Layout::STree, B,)
+    }
+
+    // Number of keys, without the padding
+    #[must_use]
+    pub fn len(&self,) -> usize {
+        self.header.count as usize
+    }
+
+    #[cfg_attr(not(test), expect(unused))]
+    #[must_use]
+    pub fn is_empty(&self,) -> bool {
+        self.len() == 0
+    }
+
+    // The stored keys, padding included, as laid out by the builder
+    #[must_use]
+    pub fn keys(&self,) -> &[K] {
+        let count = (self.header.count + self.header.padding) as usize;
+        let ptr = self.mapping.bytes()[HEADER_SIZE..].as_ptr() as *const K;
+        unsafe { std::slice::from_raw_parts(ptr, count,) }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::RngExt;
+
+    use super::*;
+    use crate::{
+        eytzinger::{self, create_layout_eytzinger},
+        stree::{self, create_layout_stree},
+    };
+
+    fn temp_path(name: &str,) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("{}-{}", name, std::process::id()),)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> =
+            (0..10_000).map(|_| rng.random_range(0..100_000,),).collect();
+        data.sort_unstable();
+        data.push(12_345,);
+        data.sort_unstable();
+
+        let eytz = create_layout_eytzinger(&data,);
+        let stree = create_layout_stree::<_, 16,>(&data,);
+
+        let eytz_path = temp_path("round-trip-eytzinger",);
+        let stree_path = temp_path("round-trip-stree",);
+        save_eytzinger(&eytz_path, &eytz,).unwrap();
+        save_stree::<_, 16,>(&stree_path, &stree, data.len(),).unwrap();
+
+        let mapped_eytz = MappedLayout::<i32,>::open_eytzinger(&eytz_path,).unwrap();
+        let mapped_stree = MappedLayout::<i32,>::open_stree::<16,>(&stree_path,).unwrap();
+
+        assert_eq!(mapped_eytz.keys(), &eytz[..]);
+        assert_eq!(mapped_stree.keys(), &stree[..]);
+        assert_eq!(mapped_stree.len(), data.len());
+        assert!(!mapped_stree.is_empty());
+
+        for _ in 0..1000 {
+            let target = rng.random_range(-10..100_010,);
+            assert_eq!(
+                eytzinger::lower_bound_prefetched_branchless(mapped_eytz.keys(), &target,),
+                eytzinger::lower_bound_prefetched_branchless(&eytz, &target,)
+            );
+            assert_eq!(
+                stree::lower_bound::<_, 16,>(mapped_stree.keys(), &target,),
+                stree::lower_bound::<_, 16,>(&stree, &target,)
+            );
+        }
+
+        std::fs::remove_file(eytz_path,).unwrap();
+        std::fs::remove_file(stree_path,).unwrap();
+    }
+
+    #[test]
+    fn test_empty() {
+        let path = temp_path("empty",);
+        save_eytzinger::<u64,>(&path, &[],).unwrap();
+
+        let mapped = MappedLayout::<u64,>::open_eytzinger(&path,).unwrap();
+        assert!(mapped.is_empty());
+        assert!(mapped.keys().is_empty());
+
+        std::fs::remove_file(path,).unwrap();
+    }
+
+    #[test]
+    fn test_validation() {
+        let data: Vec<f32,> = (0..100).map(|x| x as f32,).collect();
+        let stree = create_layout_stree::<_, 8,>(&data,);
+
+        let path = temp_path("validation",);
+        save_stree::<_, 8,>(&path, &stree, data.len(),).unwrap();
+        let bytes = std::fs::read(&path,).unwrap();
+
+        // The header must match the layout, the key type and the block size
+        assert!(MappedLayout::<f32,>::open_stree::<8,>(&path,).is_ok());
+        assert!(MappedLayout::<f32,>::open_eytzinger(&path,).is_err());
+        assert!(MappedLayout::<i32,>::open_stree::<8,>(&path,).is_err());
+        assert!(MappedLayout::<f32,>::open_stree::<16,>(&path,).is_err());
+
+        let corrupt = |offset: usize| {
+            let mut bytes = bytes.clone();
+            bytes[offset] ^= 1;
+            std::fs::write(&path, &bytes,).unwrap();
+            MappedLayout::<f32,>::open_stree::<8,>(&path,).is_err()
+        };
+
+        // Magic, version, endianness, count and a key
+        assert!(corrupt(0,));
+        assert!(corrupt(8,));
+        assert!(corrupt(12,));
+        assert!(corrupt(24,));
+        assert!(corrupt(HEADER_SIZE + 17,));
+
+        // Padding and checksum
+        assert!(corrupt(32,));
+        assert!(corrupt(40,));
+
+        // Truncated file
+        std::fs::write(&path, &bytes[..bytes.len() - 4],).unwrap();
+        assert!(MappedLayout::<f32,>::open_stree::<8,>(&path,).is_err());
+        std::fs::write(&path, &bytes[..10],).unwrap();
+        assert!(MappedLayout::<f32,>::open_stree::<8,>(&path,).is_err());
+
+        std::fs::remove_file(path,).unwrap();
+    }
+
+    #[test]
+    fn test_inconsistent_header() {
+        let data: Vec<i32,> = (0..100).collect();
+        let stree = create_layout_stree::<_, 8,>(&data,);
+        let keys = as_bytes(&stree,);
+
+        let path = temp_path("inconsistent-header",);
+
+        // Writes the keys under a header of another count and padding, with a checksum
+        // computed over it, so that only the header checks can reject it
+        let open = |layout: Layout, block: usize, count: u64, padding: u64| {
+            let mut header = Header {
+                layout,
+                key_type: i32::TYPE,
+                key_size: 4,
+                block: block as u32,
+                count,
+                padding,
+                checksum: 0,
+            };
+            header.checksum = checksum(&header.encode(), keys,);
+
+            let mut bytes = header.encode().to_vec();
+            bytes.extend_from_slice(keys,);
+            std::fs::write(&path, &bytes,).unwrap();
+
+            match layout {
+                | Layout::Eytzinger => MappedLayout::<i32,>::open_eytzinger(&path,),
+                | Layout::STree => MappedLayout::<i32,>::open_stree::<8,>(&path,),
+            }
+        };
+
+        assert!(open(Layout::STree, 8, 100, 4,).is_ok());
+        assert!(open(Layout::Eytzinger, 1, 104, 0,).is_ok());
+
+        // Padding longer than a node with the same file size, or keys ending in the
+        // middle of a node
+        assert!(open(Layout::STree, 8, 92, 12,).is_err());
+        assert!(open(Layout::STree, 8, 0, 104,).is_err());
+        assert!(open(Layout::STree, 8, 99, 4,).is_err());
+        assert!(open(Layout::STree, 8, 101, 4,).is_err());
+        assert!(open(Layout::Eytzinger, 1, 103, 1,).is_err());
+
+        // A count off by one, consistent with the padding and the file size, caught by
+        // the checksum alone
+        assert!(open(Layout::STree, 8, 100, 4,).is_ok());
+        let mut bytes = std::fs::read(&path,).unwrap();
+        bytes[24..32].copy_from_slice(&99u64.to_ne_bytes(),);
+        bytes[32..40].copy_from_slice(&5u64.to_ne_bytes(),);
+        std::fs::write(&path, &bytes,).unwrap();
+        assert!(MappedLayout::<i32,>::open_stree::<8,>(&path,).is_err());
+
+        std::fs::remove_file(path,).unwrap();
+    }
+
+    #[test]
+    #[should_panic(expected = "cannot hold")]
+    fn test_save_count_past_keys() {
+        let data: Vec<i32,> = (0..100).collect();
+        let stree = create_layout_stree::<_, 8,>(&data,);
+
+        let _ = save_stree::<_, 8,>(&temp_path("count-past-keys",), &stree, 200,);
+    }
+}
@@ -1,49 +1,214 @@
-#[cfg(target_arch = "x86_64")]
+#[cfg(simd = "x86")]
 use std::arch::x86_64::{
+    __m128i,
     __m256i,
     _mm256_castsi256_ps,
+    _mm256_cmp_pd,
+    _mm256_cmp_ps,
+    _mm256_cmpgt_epi8,
+    _mm256_cmpgt_epi16,
     _mm256_cmpgt_epi32,
+    _mm256_cmpgt_epi64,
+    _mm256_loadu_pd,
+    _mm256_loadu_ps,
     _mm256_loadu_si256,
+    _mm256_movemask_epi8,
+    _mm256_movemask_pd,
     _mm256_movemask_ps,
+    _mm256_set1_epi8,
+    _mm256_set1_epi16,
     _mm256_set1_epi32,
-    _mm_prefetch,
-    _MM_HINT_T0,
+    _mm256_set1_epi64x,
+    _mm256_set1_pd,
+    _mm256_set1_ps,
+    _mm256_xor_si256,
+    _mm512_cmp_pd_mask,
+    _mm512_cmp_ps_mask,
+    _mm512_cmple_epi8_mask,
+    _mm512_cmple_epi16_mask,
+    _mm512_cmple_epi32_mask,
+    _mm512_cmple_epi64_mask,
+    _mm512_cmple_epu8_mask,
+    _mm512_cmple_epu16_mask,
+    _mm512_cmple_epu32_mask,
+    _mm512_cmple_epu64_mask,
+    _mm512_cmplt_epi8_mask,
+    _mm512_cmplt_epi16_mask,
+    _mm512_cmplt_epi32_mask,
+    _mm512_cmplt_epi64_mask,
+    _mm512_cmplt_epu8_mask,
+    _mm512_cmplt_epu16_mask,
+    _mm512_cmplt_epu32_mask,
+    _mm512_cmplt_epu64_mask,
+    _mm512_loadu_pd,
+    _mm512_loadu_ps,
+    _mm512_loadu_si512,
+    _mm512_set1_epi8,
+    _mm512_set1_epi16,
+    _mm512_set1_epi32,
+    _mm512_set1_epi64,
+    _mm512_set1_pd,
+    _mm512_set1_ps,
+    _mm_cmpgt_epi8,
+    _mm_cmpgt_epi16,
+    _mm_cmpgt_epi32,
+    _mm_cmple_pd,
+    _mm_cmple_ps,
+    _mm_cmplt_pd,
+    _mm_cmplt_ps,
+    _mm_loadu_pd,
+    _mm_loadu_ps,
+    _mm_loadu_si128,
+    _mm_movemask_epi8,
+    _mm_movemask_pd,
+    _mm_movemask_ps,
+    _mm_set1_epi8,
+    _mm_set1_epi16,
+    _mm_set1_epi32,
+    _mm_set1_pd,
+    _mm_set1_ps,
+    _mm_xor_si128,
+    _CMP_LE_OQ,
+    _CMP_LT_OQ,
 };
-#[cfg(target_arch = "aarch64")]
-use std::arch::{
-    aarch64::{
-        vaddq_s32,
-        vaddvq_s32,
-        vcgtq_s32,
-        vdupq_n_s32,
-        vld1q_s32,
-        vreinterpretq_s32_u32,
-    },
-    asm,
+#[cfg(simd = "neon")]
+use std::arch::aarch64::{
+    vaddq_s8,
+    vaddq_s16,
+    vaddq_s32,
+    vaddq_s64,
+    vaddvq_s8,
+    vaddvq_s16,
+    vaddvq_s32,
+    vaddvq_s64,
+    vcgtq_f32,
+    vcgtq_f64,
+    vcgtq_s8,
+    vcgtq_s16,
+    vcgtq_s32,
+    vcgtq_s64,
+    vcgtq_u8,
+    vcgtq_u16,
+    vcgtq_u32,
+    vcgtq_u64,
+    vdupq_n_f32,
+    vdupq_n_f64,
+    vdupq_n_s8,
+    vdupq_n_s16,
+    vdupq_n_s32,
+    vdupq_n_s64,
+    vdupq_n_u8,
+    vdupq_n_u16,
+    vdupq_n_u32,
+    vdupq_n_u64,
+    vld1q_f32,
+    vld1q_f64,
+    vld1q_s8,
+    vld1q_s16,
+    vld1q_s32,
+    vld1q_s64,
+    vld1q_u8,
+    vld1q_u16,
+    vld1q_u32,
+    vld1q_u64,
+    vreinterpretq_s8_u8,
+    vreinterpretq_s16_u16,
+    vreinterpretq_s32_u32,
+    vreinterpretq_s64_u64,
 };
 
-const BLOCK_SIZE: usize = 16;
-const B_PLUS_ONE: usize = BLOCK_SIZE + 1;
-#[cfg(target_arch = "x86_64")]
-const PREFETCH_OFFSET: usize = 1 + 8;
-#[cfg(target_arch = "aarch64")]
-const PREFETCH_OFFSET: usize = 1;
+#[cfg(simd = "neon")]
+use crate::backend::Neon;
+#[cfg(simd = "x86")]
+use crate::backend::{Avx2, Avx512, Sse41};
+use crate::{
+    backend::{Backend, Isa, Scalar, dispatch},
+    eytzinger::unshuffle,
+};
+
+
+// Node kernels of the backend I for a key type. Each SIMD kernel compares LANES keys,
+// one register, against the target; a node of B keys is compared register by register.
+pub trait Kernels<I: Isa,>: Copy + PartialOrd {
+    const LANES: usize;
+
+    // Number of the LANES keys at ptr strictly less than the target.
+    unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize;
+    // Number of the LANES keys at ptr less than or equal to the target.
+    unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize;
+
+    // Number of the B keys of the node at ptr strictly less than the target.
+    #[inline(always)]
+    unsafe fn node_lower<const B: usize,>(ptr: *const Self, target: Self,) -> usize {
+        unsafe { registers_lower::<I, Self, B,>(ptr, target,) }
+    }
+
+    // Number of the B keys of the node at ptr less than or equal to the target.
+    #[inline(always)]
+    unsafe fn node_upper<const B: usize,>(ptr: *const Self, target: Self,) -> usize {
+        unsafe { registers_upper::<I, Self, B,>(ptr, target,) }
+    }
+}
 
+// Kernels of every backend compiled for the target
+#[cfg(simd = "x86")]
+pub trait Backends:
+    Kernels<Scalar,> + Kernels<Sse41,> + Kernels<Avx2,> + Kernels<Avx512,>
+{
+}
+#[cfg(simd = "x86")]
+impl<K: Kernels<Scalar,> + Kernels<Sse41,> + Kernels<Avx2,> + Kernels<Avx512,>,> Backends
+    for K
+{
+}
+#[cfg(simd = "neon")]
+pub trait Backends: Kernels<Scalar,> + Kernels<Neon,> {}
+#[cfg(simd = "neon")]
+impl<K: Kernels<Scalar,> + Kernels<Neon,>,> Backends for K {}
+#[cfg(simd = "none")]
+pub trait Backends: Kernels<Scalar,> {}
+#[cfg(simd = "none")]
+impl<K: Kernels<Scalar,>,> Backends for K {}
+
+// A key type the S-tree can be built on.
+pub trait Key: Copy + PartialOrd + Backends {
+    // Not less than any key, used to fill the last node.
+    const PADDING: Self;
+}
+
+// A node narrower than one register is counted with a branchless scalar loop.
 #[inline(always)]
-unsafe fn prefetch(ptr: *const i8,) {
-    #[cfg(target_arch = "x86_64")]
-    unsafe {
-        _mm_prefetch(ptr, _MM_HINT_T0,);
+unsafe fn registers_lower<I: Isa, K: Kernels<I,>, const B: usize,>(
+    ptr: *const K,
+    target: K,
+) -> usize {
+    if B < K::LANES {
+        return (0..B).map(|i| unsafe { (*ptr.add(i,) < target) as usize },).sum();
     }
-    #[cfg(target_arch = "aarch64")]
-    unsafe {
-        asm!("prfm pldl1keep, [{}]", in(reg) ptr);
+
+    (0..B / K::LANES)
+        .map(|c| unsafe { K::simd_lower(ptr.add(c * K::LANES,), target,) },)
+        .sum()
+}
+
+#[inline(always)]
+unsafe fn registers_upper<I: Isa, K: Kernels<I,>, const B: usize,>(
+    ptr: *const K,
+    target: K,
+) -> usize {
+    if B < K::LANES {
+        return (0..B).map(|i| unsafe { (*ptr.add(i,) <= target) as usize },).sum();
     }
+
+    (0..B / K::LANES)
+        .map(|c| unsafe { K::simd_upper(ptr.add(c * K::LANES,), target,) },)
+        .sum()
 }
 
+
+#[cfg(simd = "x86")]
 #[inline(always)]
 unsafe fn simd_intrinsic_lower(ptr: *const i32, target: i32,) -> usize {
-    #[cfg(target_arch = "x86_64")]
     unsafe {
         let t = _mm256_set1_epi32(target,);
 
@@ -60,7 +225,10 @@ unsafe fn simd_intrinsic_lower(ptr: *const i32, target: i32,) -> usize {
 
         cm as usize
     }
-    #[cfg(target_arch = "aarch64")]
+}
+#[cfg(simd = "neon")]
+#[inline(always)]
+unsafe fn simd_intrinsic_lower(ptr: *const i32, target: i32,) -> usize {
     unsafe {
         let t = vdupq_n_s32(target,);
 
@@ -89,9 +257,9 @@ unsafe fn simd_intrinsic_lower(ptr: *const i32, target: i32,) -> usize {
     }
 }
 
+#[cfg(simd = "x86")]
 #[inline(always)]
-fn simd_intrinsic_upper(ptr: *const i32, target: i32,) -> usize {
-    #[cfg(target_arch = "x86_64")]
+unsafe fn simd_intrinsic_upper(ptr: *const i32, target: i32,) -> usize {
     unsafe {
         let t = _mm256_set1_epi32(target,);
 
@@ -106,9 +274,12 @@ fn simd_intrinsic_upper(ptr: *const i32, target: i32,) -> usize {
 
         let cm = (b0 | (b1 << 8)).count_ones() as usize;
 
-        (16 - cm)
+        16 - cm
     }
-    #[cfg(target_arch = "aarch64")]
+}
+#[cfg(simd = "neon")]
+#[inline(always)]
+unsafe fn simd_intrinsic_upper(ptr: *const i32, target: i32,) -> usize {
     unsafe {
         let t = vdupq_n_s32(target,);
 
@@ -138,131 +309,1260 @@ fn simd_intrinsic_upper(ptr: *const i32, target: i32,) -> usize {
 }
 
 
-#[inline(always)]
-fn distribute_child_sizes(mut n: usize,) -> [usize; 17] {
-    let mut sizes = [0; 17];
+// SSE and AVX2 only have signed integer compares: unsigned keys are biased by flipping
+// their sign bit, which maps them to signed keys in the same order. The byte movemask
+// sets one bit per byte of each matching key, hence the division by the key size.
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_x86 {
+    (
+        $isa:ty,
+        $bytes:literal,
+        $reg:ty,
+        $load:ident,
+        $xor:ident,
+        $movemask:ident,
+        $t:ty,
+        $lane:ty,
+        $set1:ident,
+        $cmpgt:ident
+        $(, $node:item)*
+    ) => {
+        impl Kernels<$isa,> for $t {
+            const LANES: usize = $bytes / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let bias = $set1((<$t>::MIN as $lane) ^ <$lane>::MIN,);
+                    let t = $xor($set1(target as $lane,), bias,);
 
-    if n <= BLOCK_SIZE {
-        return sizes;
-    }
+                    let v = $load(ptr as *const $reg,);
+                    let m = $cmpgt(t, $xor(v, bias,),);
 
-    n -= BLOCK_SIZE;
+                    $movemask(m,).count_ones() as usize / size_of::<$t,>()
+                }
+            }
 
-    let mut level = 1;
-    while n > 0 {
-        let nodes_per_child = (B_PLUS_ONE as usize).pow(level - 1,);
-        let level_capacity = BLOCK_SIZE * nodes_per_child;
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let bias = $set1((<$t>::MIN as $lane) ^ <$lane>::MIN,);
+                    let t = $xor($set1(target as $lane,), bias,);
+
+                    let v = $load(ptr as *const $reg,);
+                    let m = $cmpgt($xor(v, bias,), t,);
 
-        for i in 0..B_PLUS_ONE {
-            if n == 0 {
-                break;
+                    <Self as Kernels<$isa,>>::LANES
+                        - $movemask(m,).count_ones() as usize / size_of::<$t,>()
+                }
             }
-            let take = std::cmp::min(n, level_capacity,);
-            sizes[i] += take;
-            n -= take;
+
+            $($node)*
         }
-        level += 1;
-    }
-    sizes
+    };
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_sse41 {
+    ($t:ty, $lane:ty, $set1:ident, $cmpgt:ident) => {
+        impl_kernels_x86!(
+            Sse41,
+            16,
+            __m128i,
+            _mm_loadu_si128,
+            _mm_xor_si128,
+            _mm_movemask_epi8,
+            $t,
+            $lane,
+            $set1,
+            $cmpgt
+        );
+    };
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_avx2 {
+    ($t:ty, $lane:ty, $set1:ident, $cmpgt:ident $(, $node:item)*) => {
+        impl_kernels_x86!(
+            Avx2,
+            32,
+            __m256i,
+            _mm256_loadu_si256,
+            _mm256_xor_si256,
+            _mm256_movemask_epi8,
+            $t,
+            $lane,
+            $set1,
+            $cmpgt
+            $(, $node)*
+        );
+    };
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_sse41_float {
+    ($t:ty, $load:ident, $set1:ident, $cmplt:ident, $cmple:ident, $movemask:ident) => {
+        impl Kernels<Sse41,> for $t {
+            const LANES: usize = 16 / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    $movemask($cmplt($load(ptr,), $set1(target,),),).count_ones() as usize
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    $movemask($cmple($load(ptr,), $set1(target,),),).count_ones() as usize
+                }
+            }
+        }
+    };
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_avx2_float {
+    ($t:ty, $load:ident, $set1:ident, $cmp:ident, $movemask:ident) => {
+        impl Kernels<Avx2,> for $t {
+            const LANES: usize = 32 / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let m = $cmp::<_CMP_LT_OQ,>($load(ptr,), $set1(target,),);
+
+                    $movemask(m,).count_ones() as usize
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let m = $cmp::<_CMP_LE_OQ,>($load(ptr,), $set1(target,),);
+
+                    $movemask(m,).count_ones() as usize
+                }
+            }
+        }
+    };
+}
+
+// The masks of the registers of a node, 64 keys at most, are merged before a single
+// popcount: a 16 keys i32 node takes one compare, a 32 keys one two compares and one
+// popcount.
+#[cfg(simd = "x86")]
+macro_rules! avx512_node_kernel {
+    ($name:ident, $registers:ident, | $ptr:ident, $target:ident | $mask:expr) => {
+        #[inline(always)]
+        unsafe fn $name<const B: usize,>(ptr: *const Self, target: Self,) -> usize {
+            let lanes = <Self as Kernels<Avx512,>>::LANES;
+            if B < lanes {
+                return unsafe { $registers::<Avx512, Self, B,>(ptr, target,) };
+            }
+
+            let $target = target;
+            let mut mask = 0u64;
+            for c in 0..B / lanes {
+                let $ptr = unsafe { ptr.add(c * lanes,) };
+                mask |= (unsafe { $mask } as u64) << (c * lanes);
+            }
+
+            mask.count_ones() as usize
+        }
+    };
+}
+
+// AVX-512 compares every integer width, signed or not, and floats straight into a mask
+// register, one bit per key.
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_avx512 {
+    ($t:ty, $lane:ty, $set1:ident, $cmplt:ident, $cmple:ident) => {
+        impl Kernels<Avx512,> for $t {
+            const LANES: usize = 64 / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let v = _mm512_loadu_si512(ptr as *const _,);
+
+                    $cmplt(v, $set1(target as $lane,),).count_ones() as usize
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let v = _mm512_loadu_si512(ptr as *const _,);
+
+                    $cmple(v, $set1(target as $lane,),).count_ones() as usize
+                }
+            }
+
+            avx512_node_kernel!(node_lower, registers_lower, |p, t| $cmplt(
+                _mm512_loadu_si512(p as *const _,),
+                $set1(t as $lane,),
+            ));
+
+            avx512_node_kernel!(node_upper, registers_upper, |p, t| $cmple(
+                _mm512_loadu_si512(p as *const _,),
+                $set1(t as $lane,),
+            ));
+        }
+    };
+}
+
+#[cfg(simd = "x86")]
+macro_rules! impl_kernels_avx512_float {
+    ($t:ty, $load:ident, $set1:ident, $cmp:ident) => {
+        impl Kernels<Avx512,> for $t {
+            const LANES: usize = 64 / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    $cmp::<_CMP_LT_OQ,>($load(ptr,), $set1(target,),).count_ones()
+                        as usize
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    $cmp::<_CMP_LE_OQ,>($load(ptr,), $set1(target,),).count_ones()
+                        as usize
+                }
+            }
+
+            avx512_node_kernel!(node_lower, registers_lower, |p, t| $cmp::<_CMP_LT_OQ,>(
+                $load(p,),
+                $set1(t,),
+            ));
+
+            avx512_node_kernel!(node_upper, registers_upper, |p, t| $cmp::<_CMP_LE_OQ,>(
+                $load(p,),
+                $set1(t,),
+            ));
+        }
+    };
+}
+
+// NEON has unsigned and float compares: every compare yields -1 per matching lane,
+// which are summed across the register.
+#[cfg(simd = "neon")]
+macro_rules! impl_kernels_neon {
+    (
+        $t:ty,
+        $load:ident,
+        $dup:ident,
+        $cmpgt:ident,
+        $reinterpret:ident,
+        $addv:ident
+        $(, $node:item)*
+    ) => {
+        impl Kernels<Neon,> for $t {
+            const LANES: usize = 16 / size_of::<$t,>();
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let c = $reinterpret($cmpgt($dup(target,), $load(ptr,),),);
+
+                    -($addv(c,) as i64) as usize
+                }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe {
+                    let c = $reinterpret($cmpgt($load(ptr,), $dup(target,),),);
+
+                    (<Self as Kernels<Neon,>>::LANES as i64 + $addv(c,) as i64) as usize
+                }
+            }
+
+            $($node)*
+        }
+    };
+}
+
+// The portable kernels have one key per register, compared without a branch, so that
+// a node is counted by a branchless loop over its keys. SSE4.1 has no 64 bits compare
+// and uses them for the 64 bits keys.
+macro_rules! impl_kernels_scalar {
+    ($isa:ty, $t:ty) => {
+        impl Kernels<$isa,> for $t {
+            const LANES: usize = 1;
+
+            #[inline(always)]
+            unsafe fn simd_lower(ptr: *const Self, target: Self,) -> usize {
+                unsafe { (*ptr < target) as usize }
+            }
+
+            #[inline(always)]
+            unsafe fn simd_upper(ptr: *const Self, target: Self,) -> usize {
+                unsafe { (*ptr <= target) as usize }
+            }
+        }
+    };
+}
+
+// 16 keys i32 nodes keep the hand written kernels.
+#[cfg(any(simd = "x86", simd = "neon"))]
+macro_rules! i32_node_kernels {
+    ($isa:ty) => {
+        #[inline(always)]
+        unsafe fn node_lower<const B: usize,>(ptr: *const Self, target: Self,) -> usize {
+            if B == 16 {
+                return unsafe { simd_intrinsic_lower(ptr, target,) };
+            }
+
+            unsafe { registers_lower::<$isa, Self, B,>(ptr, target,) }
+        }
+
+        #[inline(always)]
+        unsafe fn node_upper<const B: usize,>(ptr: *const Self, target: Self,) -> usize {
+            if B == 16 {
+                return unsafe { simd_intrinsic_upper(ptr, target,) };
+            }
+
+            unsafe { registers_upper::<$isa, Self, B,>(ptr, target,) }
+        }
+    };
+}
+
+macro_rules! impl_key {
+    ($t:ty, $padding:expr) => {
+        impl Key for $t {
+            const PADDING: Self = $padding;
+        }
+
+        impl_kernels_scalar!(Scalar, $t);
+    };
 }
 
+impl_key!(i8, i8::MAX);
+impl_key!(u8, u8::MAX);
+impl_key!(i16, i16::MAX);
+impl_key!(u16, u16::MAX);
+impl_key!(i32, i32::MAX);
+impl_key!(u32, u32::MAX);
+impl_key!(i64, i64::MAX);
+impl_key!(u64, u64::MAX);
+impl_key!(f32, f32::INFINITY);
+impl_key!(f64, f64::INFINITY);
+
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(i8, i8, _mm_set1_epi8, _mm_cmpgt_epi8);
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(u8, i8, _mm_set1_epi8, _mm_cmpgt_epi8);
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(i16, i16, _mm_set1_epi16, _mm_cmpgt_epi16);
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(u16, i16, _mm_set1_epi16, _mm_cmpgt_epi16);
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(i32, i32, _mm_set1_epi32, _mm_cmpgt_epi32);
+#[cfg(simd = "x86")]
+impl_kernels_sse41!(u32, i32, _mm_set1_epi32, _mm_cmpgt_epi32);
+#[cfg(simd = "x86")]
+impl_kernels_scalar!(Sse41, i64);
+#[cfg(simd = "x86")]
+impl_kernels_scalar!(Sse41, u64);
+#[cfg(simd = "x86")]
+impl_kernels_sse41_float!(
+    f32,
+    _mm_loadu_ps,
+    _mm_set1_ps,
+    _mm_cmplt_ps,
+    _mm_cmple_ps,
+    _mm_movemask_ps
+);
+#[cfg(simd = "x86")]
+impl_kernels_sse41_float!(
+    f64,
+    _mm_loadu_pd,
+    _mm_set1_pd,
+    _mm_cmplt_pd,
+    _mm_cmple_pd,
+    _mm_movemask_pd
+);
+
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(i8, i8, _mm256_set1_epi8, _mm256_cmpgt_epi8);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(u8, i8, _mm256_set1_epi8, _mm256_cmpgt_epi8);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(i16, i16, _mm256_set1_epi16, _mm256_cmpgt_epi16);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(u16, i16, _mm256_set1_epi16, _mm256_cmpgt_epi16);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(
+    i32,
+    i32,
+    _mm256_set1_epi32,
+    _mm256_cmpgt_epi32,
+    i32_node_kernels! { Avx2 }
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(u32, i32, _mm256_set1_epi32, _mm256_cmpgt_epi32);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(i64, i64, _mm256_set1_epi64x, _mm256_cmpgt_epi64);
+#[cfg(simd = "x86")]
+impl_kernels_avx2!(u64, i64, _mm256_set1_epi64x, _mm256_cmpgt_epi64);
+#[cfg(simd = "x86")]
+impl_kernels_avx2_float!(
+    f32,
+    _mm256_loadu_ps,
+    _mm256_set1_ps,
+    _mm256_cmp_ps,
+    _mm256_movemask_ps
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx2_float!(
+    f64,
+    _mm256_loadu_pd,
+    _mm256_set1_pd,
+    _mm256_cmp_pd,
+    _mm256_movemask_pd
+);
+
+
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    i8,
+    i8,
+    _mm512_set1_epi8,
+    _mm512_cmplt_epi8_mask,
+    _mm512_cmple_epi8_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    u8,
+    i8,
+    _mm512_set1_epi8,
+    _mm512_cmplt_epu8_mask,
+    _mm512_cmple_epu8_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    i16,
+    i16,
+    _mm512_set1_epi16,
+    _mm512_cmplt_epi16_mask,
+    _mm512_cmple_epi16_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    u16,
+    i16,
+    _mm512_set1_epi16,
+    _mm512_cmplt_epu16_mask,
+    _mm512_cmple_epu16_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    i32,
+    i32,
+    _mm512_set1_epi32,
+    _mm512_cmplt_epi32_mask,
+    _mm512_cmple_epi32_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    u32,
+    i32,
+    _mm512_set1_epi32,
+    _mm512_cmplt_epu32_mask,
+    _mm512_cmple_epu32_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    i64,
+    i64,
+    _mm512_set1_epi64,
+    _mm512_cmplt_epi64_mask,
+    _mm512_cmple_epi64_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512!(
+    u64,
+    i64,
+    _mm512_set1_epi64,
+    _mm512_cmplt_epu64_mask,
+    _mm512_cmple_epu64_mask
+);
+#[cfg(simd = "x86")]
+impl_kernels_avx512_float!(f32, _mm512_loadu_ps, _mm512_set1_ps, _mm512_cmp_ps_mask);
+#[cfg(simd = "x86")]
+impl_kernels_avx512_float!(f64, _mm512_loadu_pd, _mm512_set1_pd, _mm512_cmp_pd_mask);
+
+#[cfg(simd = "neon")]
+impl_kernels_neon!(i8, vld1q_s8, vdupq_n_s8, vcgtq_s8, vreinterpretq_s8_u8, vaddvq_s8);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(u8, vld1q_u8, vdupq_n_u8, vcgtq_u8, vreinterpretq_s8_u8, vaddvq_s8);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    i16,
+    vld1q_s16,
+    vdupq_n_s16,
+    vcgtq_s16,
+    vreinterpretq_s16_u16,
+    vaddvq_s16
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    u16,
+    vld1q_u16,
+    vdupq_n_u16,
+    vcgtq_u16,
+    vreinterpretq_s16_u16,
+    vaddvq_s16
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    i32,
+    vld1q_s32,
+    vdupq_n_s32,
+    vcgtq_s32,
+    vreinterpretq_s32_u32,
+    vaddvq_s32,
+    i32_node_kernels! { Neon }
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    u32,
+    vld1q_u32,
+    vdupq_n_u32,
+    vcgtq_u32,
+    vreinterpretq_s32_u32,
+    vaddvq_s32
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    i64,
+    vld1q_s64,
+    vdupq_n_s64,
+    vcgtq_s64,
+    vreinterpretq_s64_u64,
+    vaddvq_s64
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    u64,
+    vld1q_u64,
+    vdupq_n_u64,
+    vcgtq_u64,
+    vreinterpretq_s64_u64,
+    vaddvq_s64
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    f32,
+    vld1q_f32,
+    vdupq_n_f32,
+    vcgtq_f32,
+    vreinterpretq_s32_u32,
+    vaddvq_s32
+);
+#[cfg(simd = "neon")]
+impl_kernels_neon!(
+    f64,
+    vld1q_f64,
+    vdupq_n_f64,
+    vcgtq_f64,
+    vreinterpretq_s64_u64,
+    vaddvq_s64
+);
+
 
 #[must_use]
-pub fn create_layout_stree(data: &[i32],) -> Vec<i32,> {
+pub fn create_layout_stree<K: Key, const B: usize,>(data: &[K],) -> Vec<K,> {
+    const { assert!(B.is_power_of_two() && B >= 4 && B <= 64) };
+
     let n = data.len();
     if n == 0 {
         return Vec::new();
     }
 
-    let rem = n % BLOCK_SIZE;
-    let pad = if rem == 0 { 0 } else { BLOCK_SIZE - rem };
-    let mut stree = vec![i32::MAX; n + pad];
-
-    fn aux(source: &[i32], dest: &mut [i32], idx: usize,) {
-        let n = source.len();
-        if n == 0 {
+    let rem = n % B;
+    let pad = if rem == 0 { 0 } else { B - rem };
+    let mut stree = vec![K::PADDING; n + pad];
+
+    // The S-tree is a complete (B + 1)-ary tree: nodes are numbered level by level,
+    // every node is full except the last one, so filling it in-order puts each key at
+    // its place and leaves the padding at the end.
+    fn aux<K: Key, const B: usize,>(
+        source: &[K],
+        dest: &mut [K],
+        idx: usize,
+        i: &mut usize,
+    ) {
+        if idx * B >= source.len() {
             return;
         }
 
-        if n <= BLOCK_SIZE {
-            for i in 0..n {
-                dest[idx * BLOCK_SIZE + i] = source[i];
+        for slot in 0..B {
+            aux::<K, B,>(source, dest, idx * (B + 1) + slot + 1, i,);
+            if idx * B + slot < source.len() {
+                dest[idx * B + slot] = source[*i];
+                *i += 1;
             }
-            return;
         }
+        aux::<K, B,>(source, dest, idx * (B + 1) + B + 1, i,);
+    }
 
-        let child_sizes = distribute_child_sizes(n,);
+    aux::<K, B,>(data, &mut stree, 0, &mut 0,);
+    stree
+}
 
-        let mut cur = 0;
-        for i in 0..BLOCK_SIZE {
-            let c_len = child_sizes[i];
-            let pivot_val = source[cur + c_len];
-            dest[idx * BLOCK_SIZE + i] = pivot_val;
-            cur += c_len + 1;
-        }
+// Same layout, the nodes being split into contiguous chunks filled by threads threads,
+// each key found from its closed form sorted rank.
+#[must_use]
+pub fn create_layout_stree_parallel<K: Key + Send + Sync, const B: usize,>(
+    data: &[K],
+    threads: usize,
+) -> Vec<K,> {
+    const { assert!(B.is_power_of_two() && B >= 4 && B <= 64) };
+
+    let n = data.len();
+    if n == 0 {
+        return Vec::new();
+    }
 
-        let mut start = 0;
-        for i in 0..B_PLUS_ONE {
-            let c_len = child_sizes[i];
-            if c_len > 0 {
-                let child_node_idx = idx * B_PLUS_ONE + i + 1;
-                if child_node_idx * BLOCK_SIZE < dest.len() {
-                    aux(&source[start..start + c_len], dest, child_node_idx,);
+    let rem = n % B;
+    let pad = if rem == 0 { 0 } else { B - rem };
+    let mut stree = vec![K::PADDING; n + pad];
+    let chunk = n.div_ceil(threads.max(1,),);
+
+    std::thread::scope(|scope| {
+        for (c, slots,) in stree[..n].chunks_mut(chunk,).enumerate() {
+            scope.spawn(move || {
+                for (offset, slot,) in slots.iter_mut().enumerate() {
+                    *slot = data[index_to_rank::<B,>(n, c * chunk + offset,)];
                 }
-            }
-            start += c_len + 1;
+            },);
         }
-    }
+    },);
 
-    aux(data, &mut stree, 0,);
     stree
 }
 
+// Same layout built in place, last level first, as for the Eytzinger layout: in sorted
+// order every full last level node is followed by a key of the levels above, the keys
+// after the last leaf key all belong to the levels above, which form a perfect tree.
+// The room for the padding is reserved before the keys are moved, so that the buffer is
+// only reallocated while it holds them in sorted order. A buffer with a capacity of at
+// least n + B - 1 keys is never reallocated.
+pub fn create_layout_stree_in_place<K: Key, const B: usize,>(data: &mut Vec<K,>,) {
+    const { assert!(B.is_power_of_two() && B >= 4 && B <= 64) };
+
+    let n = data.len();
+    let rem = n % B;
+    let pad = if rem == 0 { 0 } else { B - rem };
+    data.reserve_exact(pad,);
+
+    let mut len = n;
+
+    while len > B {
+        let (_, last_nodes, last_keys,) = tree_shape(len, B,);
+        let groups = last_nodes - 1;
+        let leaves = groups * B + last_keys;
+
+        unshuffle(&mut data[..groups * (B + 1)], B + 1,);
+        data[groups..len].rotate_left(leaves,);
+
+        len -= leaves;
+    }
+
+    data.resize(n + pad, K::PADDING,);
+}
+
 
+// Shape of the complete S-tree holding n keys: every node is full except the last
+// one, which lives on the last level. Returns the number of levels, the number of
+// nodes on the last level and the number of keys in the last node.
+#[inline(always)]
+fn tree_shape(n: usize, block: usize,) -> (usize, usize, usize,) {
+    let blocks = n.div_ceil(block,);
+
+    let mut height = 1;
+    let mut level_start = 0;
+    let mut level_len = 1;
+    while level_start + level_len < blocks {
+        level_start += level_len;
+        level_len *= block + 1;
+        height += 1;
+    }
+
+    (height, blocks - level_start, n - (blocks - 1) * block,)
+}
+
+// Number of leaf keys strictly before an in-order position of the perfect tree.
+#[inline(always)]
+fn leaf_keys_before(perfect: usize, block: usize,) -> usize {
+    block * (perfect / (block + 1)) + std::cmp::min(perfect % (block + 1), block,)
+}
+
+// Like the Eytzinger layout, the sorted rank of a key is its in-order position in the
+// perfect tree of the same height, minus the missing leaf keys which come before it.
+#[must_use]
+#[inline(always)]
+pub fn index_to_rank<const B: usize,>(n: usize, idx: usize,) -> usize {
+    debug_assert!(idx < n);
+
+    let block = B;
+    let (height, last_nodes, last_keys,) = tree_shape(n, block,);
+    let present_end = (block + 1) * (last_nodes - 1) + last_keys;
+
+    let (mut node, slot,) = (idx / block, idx % block,);
+    let mut level_len = 1;
+    let mut depth = 0;
+    while node >= level_len {
+        node -= level_len;
+        level_len *= block + 1;
+        depth += 1;
+    }
+
+    let below = (block + 1).pow((height - 1 - depth) as u32,);
+    let perfect = node * below * (block + 1) + (slot + 1) * below - 1;
+
+    perfect
+        - (leaf_keys_before(perfect, block,)
+            - leaf_keys_before(std::cmp::min(perfect, present_end,), block,))
+}
+
+#[must_use]
+#[inline(always)]
+pub fn rank_to_index<const B: usize,>(n: usize, rank: usize,) -> usize {
+    debug_assert!(rank < n);
+
+    let block = B;
+    let (height, last_nodes, last_keys,) = tree_shape(n, block,);
+    let present_end = (block + 1) * (last_nodes - 1) + last_keys;
+
+    // Past the last present leaf key, only internal keys remain.
+    let perfect = if rank < present_end {
+        rank
+    } else {
+        (block + 1) * (rank - leaf_keys_before(present_end, block,)) + block
+    };
+
+    let mut x = perfect + 1;
+    let mut depth = height - 1;
+    while x % (block + 1) == 0 {
+        x /= block + 1;
+        depth -= 1;
+    }
+
+    let level_start = ((block + 1).pow(depth as u32,) - 1) / block;
+    (level_start + x / (block + 1)) * block + x % (block + 1) - 1
+}
+
+
+// The node kernels read whole blocks: the layout must hold the n keys, padded to a
+// multiple of B.
+#[inline(always)]
+fn check_layout<K, const B: usize,>(data: &[K], n: usize,) {
+    assert!(
+        n <= data.len() && data.len() == n.div_ceil(B,) * B,
+        "a layout of {} keys does not hold {n} keys in blocks of {B}",
+        data.len()
+    );
+}
+
+// Every search comes with a _with variant running the kernels of the given backend, the
+// plain one runs those of Backend::current().
 macro_rules! impl_bound_stree {
-    ($name:ident, $simd_intrinsic:ident) => {
-        pub fn $name(data: &[i32], target: &i32,) -> Option<usize,> {
-            let base_ptr = data.as_ptr();
-            let n = data.len();
-            if n == 0 {
-                return None;
-            }
+    (
+        $(#[$attr:meta])* $name:ident,
+        $name_with:ident,
+        $(#[$rank_attr:meta])* $rank_name:ident,
+        $rank_name_with:ident,
+        $simd_intrinsic:ident
+    ) => {
+        $(#[$attr])*
+        pub fn $name<K: Key, const B: usize,>(data: &[K], target: &K,) -> Option<usize,> {
+            $name_with::<K, B,>(Backend::current(), data, target,)
+        }
 
-            let mut res: Option<usize,> = None;
-            let mut cur = 0;
-            let t = *target;
+        pub fn $name_with<K: Key, const B: usize,>(
+            backend: Backend,
+            data: &[K],
+            target: &K,
+        ) -> Option<usize,> {
+            check_layout::<K, B,>(data, data.len(),);
+
+            #[inline(always)]
+            fn search<I: Isa, K: Key + Kernels<I,>, const B: usize,>(
+                data: &[K],
+                target: &K,
+            ) -> Option<usize,> {
+                let base_ptr = data.as_ptr();
+                let n = data.len();
+                if n == 0 {
+                    return None;
+                }
 
-            while (cur * BLOCK_SIZE) < n {
-                let block_offset = cur * BLOCK_SIZE;
-                let ptr = unsafe { base_ptr.add(block_offset,) };
+                let mut res: Option<usize,> = None;
+                let mut cur = 0;
+                let t = *target;
 
-                unsafe {
-                    let lookahead_idx = (17 * cur + PREFETCH_OFFSET) * 16;
-                    if lookahead_idx < n {
-                        let ptr = base_ptr.add(lookahead_idx,) as *const i8;
-                        prefetch(ptr,);
+                while (cur * B) < n {
+                    let block_offset = cur * B;
+                    let ptr = unsafe { base_ptr.add(block_offset,) };
+
+                    unsafe {
+                        let lookahead_idx = ((B + 1) * cur + I::prefetch_offset(B,)) * B;
+                        if lookahead_idx < n {
+                            let ptr = base_ptr.add(lookahead_idx,) as *const i8;
+                            I::prefetch(ptr,);
+                        }
                     }
+
+                    let i = unsafe { <K as Kernels<I,>>::$simd_intrinsic::<B,>(ptr, t,) };
+
+                    if i < B {
+                        let candidate_val = unsafe { *ptr.add(i,) };
+                        if candidate_val != K::PADDING {
+                            res = Some(block_offset + i,);
+                        }
+                    }
+
+                    cur = cur * (B + 1) + i + 1;
+                }
+
+                res
+            }
+
+            dispatch!(backend, I => search::<I, K, B,>(data, target,))
+        }
+
+        // Only keys stored before position n are real, the others are padding.
+        $(#[$rank_attr])*
+        pub fn $rank_name<K: Key, const B: usize,>(
+            data: &[K],
+            n: usize,
+            target: &K,
+        ) -> usize {
+            $rank_name_with::<K, B,>(Backend::current(), data, n, target,)
+        }
+
+        pub fn $rank_name_with<K: Key, const B: usize,>(
+            backend: Backend,
+            data: &[K],
+            n: usize,
+            target: &K,
+        ) -> usize {
+            check_layout::<K, B,>(data, n,);
+
+            #[inline(always)]
+            fn search<I: Isa, K: Key + Kernels<I,>, const B: usize,>(
+                data: &[K],
+                n: usize,
+                target: &K,
+            ) -> usize {
+                let base_ptr = data.as_ptr();
+                if n == 0 {
+                    return 0;
                 }
 
-                let i = unsafe { $simd_intrinsic(ptr, t,) };
+                let mut res: Option<usize,> = None;
+                let mut cur = 0;
+                let t = *target;
+
+                while (cur * B) < n {
+                    let block_offset = cur * B;
+                    let ptr = unsafe { base_ptr.add(block_offset,) };
+
+                    unsafe {
+                        let lookahead_idx = ((B + 1) * cur + I::prefetch_offset(B,)) * B;
+                        if lookahead_idx < n {
+                            let ptr = base_ptr.add(lookahead_idx,) as *const i8;
+                            I::prefetch(ptr,);
+                        }
+                    }
+
+                    let i = unsafe { <K as Kernels<I,>>::$simd_intrinsic::<B,>(ptr, t,) };
 
-                if i < 16 {
-                    let candidate_val = unsafe { *ptr.add(i,) };
-                    if candidate_val != i32::MAX {
+                    if i < B && block_offset + i < n {
                         res = Some(block_offset + i,);
                     }
+
+                    cur = cur * (B + 1) + i + 1;
+                }
+
+                res.map_or(n, |i| index_to_rank::<B,>(n, i,),)
+            }
+
+            dispatch!(backend, I => search::<I, K, B,>(data, n, target,))
+        }
+    };
+}
+
+impl_bound_stree!(
+    lower_bound,
+    lower_bound_with,
+    lower_bound_rank,
+    lower_bound_rank_with,
+    node_lower
+);
+impl_bound_stree!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound,
+    upper_bound_with,
+    upper_bound_rank,
+    upper_bound_rank_with,
+    node_upper
+);
+
+
+// Both bounds come from the SIMD node kernels, over the same n real keys
+#[must_use]
+pub fn equal_range<K: Key, const B: usize,>(
+    data: &[K],
+    n: usize,
+    target: &K,
+) -> (usize, usize,) {
+    check_layout::<K, B,>(data, n,);
+    (
+        lower_bound_rank::<K, B,>(data, n, target,),
+        upper_bound_rank::<K, B,>(data, n, target,),
+    )
+}
+
+#[must_use]
+pub fn count<K: Key, const B: usize,>(data: &[K], n: usize, target: &K,) -> usize {
+    let (lower, upper,) = equal_range::<K, B,>(data, n, target,);
+    upper - lower
+}
+
+// Number of keys in the half-open range [lo, hi)
+#[must_use]
+pub fn count_in_range<K: Key, const B: usize,>(
+    data: &[K],
+    n: usize,
+    lo: &K,
+    hi: &K,
+) -> usize {
+    check_layout::<K, B,>(data, n,);
+    lower_bound_rank::<K, B,>(data, n, hi,)
+        .saturating_sub(lower_bound_rank::<K, B,>(data, n, lo,),)
+}
+
+
+// Software pipelined searches: P queries are in flight, each one a step of the tree
+// deeper than the next, and every step prefetches the block the query needs next.
+// The block is only read P steps later, once the other queries have made theirs.
+macro_rules! impl_bound_stree_batch {
+    ($(#[$attr:meta])* $name:ident, $name_with:ident, $simd_intrinsic:ident) => {
+        $(#[$attr])*
+        pub fn $name<K: Key, const B: usize, const P: usize,>(
+            data: &[K],
+            targets: &[K],
+            results: &mut [Option<usize,>],
+        ) {
+            $name_with::<K, B, P,>(Backend::current(), data, targets, results,);
+        }
+
+        pub fn $name_with<K: Key, const B: usize, const P: usize,>(
+            backend: Backend,
+            data: &[K],
+            targets: &[K],
+            results: &mut [Option<usize,>],
+        ) {
+            check_layout::<K, B,>(data, data.len(),);
+
+            #[inline(always)]
+            fn batch<I: Isa, K: Key + Kernels<I,>, const B: usize, const P: usize,>(
+                data: &[K],
+                targets: &[K],
+                results: &mut [Option<usize,>],
+            ) {
+                const { assert!(P > 0) };
+                assert_eq!(targets.len(), results.len());
+
+                let base_ptr = data.as_ptr();
+                let n = data.len();
+                if n == 0 {
+                    results.fill(None,);
+                    return;
+                }
+
+                // Node, query and result of every slot of the pipeline
+                let mut cur = [0; P];
+                let mut query = [0; P];
+                let mut res: [Option<usize,>; P] = [None; P];
+                let mut busy = [false; P];
+
+                let mut next = 0;
+                let mut in_flight = 0;
+
+                while next < targets.len() || in_flight > 0 {
+                    // The first queries enter one per round, so that they start at
+                    // different depths. Later ones take the slot of a finished query
+                    // right away.
+                    let mut admitted = false;
+
+                    for s in 0..P {
+                        if !busy[s] {
+                            if next == targets.len() || (admitted && next < P) {
+                                continue;
+                            }
+
+                            (cur[s], query[s], res[s], busy[s],) = (0, next, None, true,);
+                            next += 1;
+                            in_flight += 1;
+                            admitted = true;
+                        }
+
+                        let block_offset = cur[s] * B;
+                        let ptr = unsafe { base_ptr.add(block_offset,) };
+                        let i = unsafe {
+                            <K as Kernels<I,>>::$simd_intrinsic::<B,>(
+                                ptr,
+                                targets[query[s]],
+                            )
+                        };
+
+                        if i < B {
+                            let candidate_val = unsafe { *ptr.add(i,) };
+                            if candidate_val != K::PADDING {
+                                res[s] = Some(block_offset + i,);
+                            }
+                        }
+
+                        cur[s] = cur[s] * (B + 1) + i + 1;
+
+                        if cur[s] * B < n {
+                            // Blocks are not aligned on cache lines, fetch all those
+                            // they span
+                            let block = unsafe { base_ptr.add(cur[s] * B,) as *const i8 };
+                            let bytes = B * size_of::<K,>();
+                            for offset in (0..bytes).step_by(64,) {
+                                unsafe { I::prefetch(block.add(offset,),) };
+                            }
+                            unsafe { I::prefetch(block.add(bytes - 1,),) };
+                        } else {
+                            results[query[s]] = res[s];
+                            busy[s] = false;
+                            in_flight -= 1;
+                        }
+                    }
+                }
+            }
+
+            dispatch!(backend, I => batch::<I, K, B, P,>(data, targets, results,));
+        }
+    };
+}
+
+impl_bound_stree_batch!(lower_bound_batch, lower_bound_batch_with, node_lower);
+impl_bound_stree_batch!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_batch,
+    upper_bound_batch_with,
+    node_upper
+);
+
+
+// Same recursion as the Eytzinger sorted batch search, over B + 1 children: the
+// queries of a node are split by each of its keys in turn.
+macro_rules! impl_bound_stree_sorted_batch {
+    ($(#[$attr:meta])* $name:ident, $go_left:ident) => {
+        $(#[$attr])*
+        pub fn $name<K: Key, const B: usize,>(
+            data: &[K],
+            n: usize,
+            targets: &[K],
+            ranks: &mut [usize],
+        ) {
+            check_layout::<K, B,>(data, n,);
+            assert_eq!(targets.len(), ranks.len());
+            assert!(targets.is_sorted(), "queries of a sorted batch must be sorted");
+
+            fn aux<K: Key, const B: usize,>(
+                data: &[K],
+                n: usize,
+                cur: usize,
+                targets: &[K],
+                ranks: &mut [usize],
+                res: Option<usize,>,
+            ) {
+                if targets.is_empty() {
+                    return;
                 }
 
-                cur = cur * 17 + i + 1;
+                let block_offset = cur * B;
+                if block_offset >= n {
+                    ranks.fill(res.map_or(n, |i| index_to_rank::<B,>(n, i,),),);
+                    return;
+                }
+
+                let (mut targets, mut ranks,) = (targets, ranks,);
+                for c in 0..=B {
+                    // Queries of child c go right of the keys before slot c, and left
+                    // of the key in slot c, which is then their candidate.
+                    let (split, candidate,) = if c < B {
+                        let key = data[block_offset + c];
+                        let split = targets.partition_point(|t| key.$go_left(t,),);
+                        let candidate = if block_offset + c < n {
+                            Some(block_offset + c,)
+                        } else {
+                            res
+                        };
+                        (split, candidate,)
+                    } else {
+                        (targets.len(), res,)
+                    };
+
+                    let (child_ranks, rest,) = ranks.split_at_mut(split,);
+                    aux::<K, B,>(
+                        data,
+                        n,
+                        cur * (B + 1) + c + 1,
+                        &targets[..split],
+                        child_ranks,
+                        candidate,
+                    );
+
+                    (targets, ranks,) = (&targets[split..], rest,);
+                }
             }
 
-            res
+            aux::<K, B,>(data, n, 0, targets, ranks, None,);
         }
     };
 }
 
-impl_bound_stree!(lower_bound, simd_intrinsic_lower);
-impl_bound_stree!(upper_bound, simd_intrinsic_upper);
+impl_bound_stree_sorted_batch!(lower_bound_sorted_batch, ge);
+impl_bound_stree_sorted_batch!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound_sorted_batch,
+    gt
+);
+
+
+const MAX_DEPTH: usize = usize::BITS as usize;
+
+// Search path left by the previous finger search: the node visited at each depth, and
+// the in-order neighbours of its subtree, the keys of its ancestors just before and
+// just after it.
+pub struct Finger {
+    depth:  usize,
+    nodes:  [usize; MAX_DEPTH],
+    before: [Option<usize,>; MAX_DEPTH],
+    after:  [Option<usize,>; MAX_DEPTH],
+}
+
+impl Finger {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            depth:  0,
+            nodes:  [0; MAX_DEPTH],
+            before: [None; MAX_DEPTH],
+            after:  [None; MAX_DEPTH],
+        }
+    }
+}
+
+impl Default for Finger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Same restart rule as the Eytzinger finger search: climb the previous path up to the
+// deepest node whose subtree holds the answer, then go down with the node kernels.
+macro_rules! impl_bound_stree_finger {
+    (
+        $(#[$attr:meta])* $name:ident,
+        $name_with:ident,
+        $simd_intrinsic:ident,
+        $go_left:ident
+    ) => {
+        $(#[$attr])*
+        pub fn $name<K: Key, const B: usize,>(
+            data: &[K],
+            n: usize,
+            target: &K,
+            finger: &mut Finger,
+        ) -> Option<usize,> {
+            $name_with::<K, B,>(Backend::current(), data, n, target, finger,)
+        }
+
+        pub fn $name_with<K: Key, const B: usize,>(
+            backend: Backend,
+            data: &[K],
+            n: usize,
+            target: &K,
+            finger: &mut Finger,
+        ) -> Option<usize,> {
+            check_layout::<K, B,>(data, n,);
+
+            #[inline(always)]
+            fn search<I: Isa, K: Key + Kernels<I,>, const B: usize,>(
+                data: &[K],
+                n: usize,
+                target: &K,
+                finger: &mut Finger,
+            ) -> Option<usize,> {
+                let base_ptr = data.as_ptr();
+                let t = *target;
+
+                let mut depth = finger.depth.saturating_sub(1,);
+                while depth > 0
+                    && (finger.before[depth].is_some_and(|i| data[i].$go_left(&t,),)
+                        || finger.after[depth].is_some_and(|i| !data[i].$go_left(&t,),))
+                {
+                    depth -= 1;
+                }
+
+                let (mut cur, mut before, mut after,) = if finger.depth == 0 {
+                    (0, None, None,)
+                } else {
+                    (finger.nodes[depth], finger.before[depth], finger.after[depth],)
+                };
+
+                while (cur * B) < n {
+                    finger.nodes[depth] = cur;
+                    finger.before[depth] = before;
+                    finger.after[depth] = after;
+
+                    let block_offset = cur * B;
+                    let ptr = unsafe { base_ptr.add(block_offset,) };
+
+                    let i = unsafe { <K as Kernels<I,>>::$simd_intrinsic::<B,>(ptr, t,) };
+
+                    if i > 0 {
+                        before = Some(block_offset + i - 1,);
+                    }
+                    if i < B && block_offset + i < n {
+                        after = Some(block_offset + i,);
+                    }
+
+                    cur = cur * (B + 1) + i + 1;
+                    depth += 1;
+                }
+
+                finger.depth = depth;
+                after
+            }
+
+            dispatch!(backend, I => search::<I, K, B,>(data, n, target, finger,))
+        }
+    };
+}
+
+impl_bound_stree_finger!(lower_bound_finger, lower_bound_finger_with, node_lower, ge);
+impl_bound_stree_finger!(
+    #[expect(unused)]
+    upper_bound_finger,
+    upper_bound_finger_with,
+    node_upper,
+    gt
+);
 
 #[cfg(test)]
 mod tests {
@@ -293,7 +1593,7 @@ mod tests {
             1, 2, 6, 7, 17, 19, 21, 24, 29, 33, 35, 38, 40, 45, 47, 49, 52, 54, 55, 61,
             62, 64, 75, 76, 77, 79, 81, 83, 84, 85, 88, 96,
         ];
-        let layout = create_layout_stree(&input,);
+        let layout = create_layout_stree::<_, 16,>(&input,);
 
         assert_eq!(
             layout,
@@ -307,7 +1607,7 @@ mod tests {
             1, 2, 6, 7, 17, 19, 21, 24, 29, 33, 35, 38, 40, 45, 47, 49, 52, 54, 55, 61,
             62, 64, 75, 76, 77, 79, 81,
         ];
-        let layout = create_layout_stree(&input,);
+        let layout = create_layout_stree::<_, 16,>(&input,);
 
         assert_eq!(
             layout,
@@ -323,29 +1623,30 @@ mod tests {
     fn test_lower_bound_basic() {
         // Layout: [10, 20, 20, 20, 30]
         let data = vec![10, 20, 20, 20, 30];
-        let layout = create_layout_stree(&data,);
+        let layout = create_layout_stree::<_, 16,>(&data,);
 
         // Case 1: Target smaller than all (5) -> Returns 10
         // (First element >= 5 is 10)
-        let res = lower_bound(&layout, &5,).unwrap();
+        let res = lower_bound::<_, 16,>(&layout, &5,).unwrap();
         assert_eq!(layout[res], 10, "Target 5 should find 10");
 
         // Case 2: Target exact match (10) -> Returns 10
-        let res = lower_bound(&layout, &10,).unwrap();
+        let res = lower_bound::<_, 16,>(&layout, &10,).unwrap();
         assert_eq!(layout[res], 10, "Target 10 should find 10");
 
         // Case 3: Target duplicates (20) -> Returns 20
-        // (It should find one of the 20s)
-        let res = lower_bound(&layout, &20,).unwrap();
+        // (It should find one of the 20s, the rank gives the first one)
+        let res = lower_bound::<_, 16,>(&layout, &20,).unwrap();
         assert_eq!(layout[res], 20, "Target 20 should find 20");
+        assert_eq!(lower_bound_rank::<_, 16,>(&layout, data.len(), &20,), 1);
 
         // Case 4: Target in-between (25) -> Returns 30
         // (First element >= 25 is 30)
-        let res = lower_bound(&layout, &25,).unwrap();
+        let res = lower_bound::<_, 16,>(&layout, &25,).unwrap();
         assert_eq!(layout[res], 30, "Target 25 should find 30");
 
         // Case 5: Target larger than all (40) -> Returns None
-        let res = lower_bound(&layout, &40,);
+        let res = lower_bound::<_, 16,>(&layout, &40,);
         assert!(res.is_none(), "Target 40 should return None");
     }
 
@@ -358,7 +1659,7 @@ mod tests {
         let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
         data.sort_unstable(); // Must be sorted
 
-        let layout = create_layout_stree(&data,);
+        let layout = create_layout_stree::<_, 16,>(&data,);
 
         // 2. Test random targets
         for _ in 0..1000 {
@@ -369,7 +1670,7 @@ mod tests {
             let truth_val = truth_idx.map(|i| data[i],);
 
             // Get S-Tree Result
-            let stree_idx = lower_bound(&layout, &target,);
+            let stree_idx = lower_bound::<_, 16,>(&layout, &target,);
             let stree_val = stree_idx.map(|i| layout[i],);
 
             assert_eq!(
@@ -386,26 +1687,26 @@ mod tests {
         // Note: We need enough elements or padding to handle the block logic,
         // but create_layout_stree handles that.
         let data = vec![10, 20, 20, 20, 30];
-        let layout = create_layout_stree(&data,);
+        let layout = create_layout_stree::<_, 16,>(&data,);
 
         // Case 1: Target smaller than all (5) -> Returns 10
-        let res = upper_bound(&layout, &5,).unwrap();
+        let res = upper_bound::<_, 16,>(&layout, &5,).unwrap();
         assert_eq!(layout[res], 10, "Target 5 should find 10");
 
         // Case 2: Target exists (10) -> Returns 20 (Strictly greater)
-        let res = upper_bound(&layout, &10,).unwrap();
+        let res = upper_bound::<_, 16,>(&layout, &10,).unwrap();
         assert_eq!(layout[res], 20, "Target 10 should find 20");
 
         // Case 3: Target duplicates (20) -> Returns 30 (Skip all 20s)
-        let res = upper_bound(&layout, &20,).unwrap();
+        let res = upper_bound::<_, 16,>(&layout, &20,).unwrap();
         assert_eq!(layout[res], 30, "Target 20 should find 30");
 
         // Case 4: Target exists (30) -> Returns None (End of array)
-        let res = upper_bound(&layout, &30,);
+        let res = upper_bound::<_, 16,>(&layout, &30,);
         assert!(res.is_none(), "Target 30 should return None");
 
         // Case 5: Target huge (100) -> Returns None
-        let res = upper_bound(&layout, &100,);
+        let res = upper_bound::<_, 16,>(&layout, &100,);
         assert!(res.is_none(), "Target 100 should return None");
     }
 
@@ -418,7 +1719,7 @@ mod tests {
         let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
         data.sort_unstable(); // Must be sorted
 
-        let layout = create_layout_stree(&data,);
+        let layout = create_layout_stree::<_, 16,>(&data,);
 
         // 2. Test random targets
         for _ in 0..1000 {
@@ -429,7 +1730,7 @@ mod tests {
             let truth_val = truth_idx.map(|i| data[i],);
 
             // Get S-Tree Result
-            let stree_idx = upper_bound(&layout, &target,);
+            let stree_idx = upper_bound::<_, 16,>(&layout, &target,);
             let stree_val = stree_idx.map(|i| layout[i],);
 
             assert_eq!(
@@ -439,4 +1740,490 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_rank_layout_structure() {
+        let input: Vec<i32,> = (0..27).collect();
+        let layout = create_layout_stree::<_, 16,>(&input,);
+
+        // Every value is its own sorted rank
+        for (idx, &rank,) in layout.iter().take(input.len(),).enumerate() {
+            assert_eq!(index_to_rank::<16,>(input.len(), idx,), rank as usize);
+        }
+    }
+
+    #[test]
+    fn test_rank_round_trip() {
+        let sizes = (1..2000).chain([
+            16 * 17,
+            16 * 17 + 1,
+            16 * 17 * 17 + 16,
+            16 * 17 * 17 + 7,
+            100_003,
+        ],);
+
+        for n in sizes {
+            let input: Vec<i32,> = (0..n as i32).collect();
+            let layout = create_layout_stree::<_, 16,>(&input,);
+
+            for (idx, &rank,) in layout.iter().take(n,).enumerate() {
+                let rank = rank as usize;
+                assert_eq!(
+                    index_to_rank::<16,>(n, idx,),
+                    rank,
+                    "n = {}, idx = {}",
+                    n,
+                    idx
+                );
+                assert_eq!(
+                    rank_to_index::<16,>(n, rank,),
+                    idx,
+                    "n = {}, rank = {}",
+                    n,
+                    rank
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_rank_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [1, 15, 16, 17, 300, 2000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..100,),).collect();
+            data.sort_unstable();
+
+            let layout = create_layout_stree::<_, 16,>(&data,);
+
+            for _ in 0..1000 {
+                let target = rng.random_range(0..110,);
+
+                assert_eq!(
+                    lower_bound_rank::<_, 16,>(&layout, data.len(), &target,),
+                    data.partition_point(|&x| x < target,),
+                    "Lower bound mismatch for target {}",
+                    target
+                );
+                assert_eq!(
+                    upper_bound_rank::<_, 16,>(&layout, data.len(), &target,),
+                    data.partition_point(|&x| x <= target,),
+                    "Upper bound mismatch for target {}",
+                    target
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_rank_padding_value() {
+        // Real keys equal to the padding value must still be found
+        let data = vec![1, 5, i32::MAX, i32::MAX];
+        let layout = create_layout_stree::<_, 16,>(&data,);
+
+        assert_eq!(lower_bound_rank::<_, 16,>(&layout, data.len(), &i32::MAX,), 2);
+        assert_eq!(upper_bound_rank::<_, 16,>(&layout, data.len(), &i32::MAX,), 4);
+        assert_eq!(upper_bound_rank::<_, 16,>(&layout, data.len(), &5,), 2);
+    }
+
+    #[test]
+    #[should_panic(expected = "does not hold")]
+    fn test_bound_rank_layout_mismatch() {
+        // A count past the layout would make the node kernels read out of it
+        let data: Vec<i32,> = (0..20).collect();
+        let layout = create_layout_stree::<_, 16,>(&data,);
+
+        let _ = lower_bound_rank::<_, 16,>(&layout, 40, &7,);
+    }
+
+    // Checks every target against std with the kernels of every supported backend, on
+    // data drawn with heavy duplicates from the pool, which also holds the extreme
+    // values of the key type.
+    fn check_key_type<K: Key + std::fmt::Debug, const B: usize,>(pool: &[K],) {
+        let mut rng = rand::rng();
+
+        for n in [1, 100, 2000,] {
+            let mut data: Vec<K,> =
+                (0..n).map(|_| pool[rng.random_range(0..pool.len(),)],).collect();
+            data.sort_unstable_by(|a, b| a.partial_cmp(b,).unwrap(),);
+
+            let layout = create_layout_stree::<K, B,>(&data,);
+            assert_eq!(layout.len() % B, 0);
+
+            for backend in Backend::supported() {
+                for target in pool {
+                    assert_eq!(
+                        lower_bound_rank_with::<K, B,>(backend, &layout, n, target,),
+                        data.partition_point(|x| x < target,),
+                        "Lower bound mismatch for target {:?}, B = {}, {:?}",
+                        target,
+                        B,
+                        backend
+                    );
+                    assert_eq!(
+                        upper_bound_rank_with::<K, B,>(backend, &layout, n, target,),
+                        data.partition_point(|x| x <= target,),
+                        "Upper bound mismatch for target {:?}, B = {}, {:?}",
+                        target,
+                        B,
+                        backend
+                    );
+                }
+            }
+        }
+    }
+
+    fn check_key_type_widths<K: Key + std::fmt::Debug,>(pool: &[K],) {
+        check_key_type::<K, 4,>(pool,);
+        check_key_type::<K, 8,>(pool,);
+        check_key_type::<K, 16,>(pool,);
+        check_key_type::<K, 32,>(pool,);
+        check_key_type::<K, 64,>(pool,);
+    }
+
+    macro_rules! test_key_type {
+        ($name:ident, $t:ty) => {
+            #[test]
+            fn $name() {
+                let mut rng = rand::rng();
+
+                let mut pool: Vec<$t,> = (0..50).map(|_| rng.random(),).collect();
+                pool.extend([<$t>::MIN, <$t>::MIN + 1, <$t>::MAX - 1, <$t>::MAX,],);
+                pool.extend([0 as $t, 1 as $t,],);
+
+                check_key_type_widths(&pool,);
+            }
+        };
+    }
+
+    test_key_type!(test_key_i8, i8);
+    test_key_type!(test_key_u8, u8);
+    test_key_type!(test_key_i16, i16);
+    test_key_type!(test_key_u16, u16);
+    test_key_type!(test_key_i32, i32);
+    test_key_type!(test_key_u32, u32);
+    test_key_type!(test_key_i64, i64);
+    test_key_type!(test_key_u64, u64);
+
+    #[test]
+    fn test_key_floats() {
+        let mut rng = rand::rng();
+
+        let mut pool: Vec<f32,> =
+            (0..50).map(|_| rng.random_range(-1e6..1e6,),).collect();
+        pool.extend([f32::NEG_INFINITY, f32::MIN, -0.0, 0.0, f32::MAX, f32::INFINITY,],);
+        check_key_type_widths(&pool,);
+
+        let mut pool: Vec<f64,> =
+            (0..50).map(|_| rng.random_range(-1e12..1e12,),).collect();
+        pool.extend([f64::NEG_INFINITY, f64::MIN, -0.0, 0.0, f64::MAX, f64::INFINITY,],);
+        check_key_type_widths(&pool,);
+    }
+
+    // Same checks as the fuzzy tests above, for every node width.
+    fn check_width<const B: usize,>() {
+        let mut rng = rand::rng();
+
+        let n = 2000;
+        let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+
+        let layout = create_layout_stree::<_, B,>(&data,);
+
+        for _ in 0..1000 {
+            let target = rng.random_range(0..110,);
+
+            let truth_idx = std_lower_bound(&data, target,);
+            let stree_idx = lower_bound::<_, B,>(&layout, &target,);
+            assert_eq!(
+                stree_idx.map(|i| layout[i],),
+                truth_idx.map(|i| data[i],),
+                "B = {}",
+                B
+            );
+            assert_eq!(
+                lower_bound_rank::<_, B,>(&layout, data.len(), &target,),
+                data.partition_point(|&x| x < target,),
+                "B = {}",
+                B
+            );
+
+            let truth_idx = std_upper_bound(&data, target,);
+            let stree_idx = upper_bound::<_, B,>(&layout, &target,);
+            assert_eq!(
+                stree_idx.map(|i| layout[i],),
+                truth_idx.map(|i| data[i],),
+                "B = {}",
+                B
+            );
+            assert_eq!(
+                upper_bound_rank::<_, B,>(&layout, data.len(), &target,),
+                data.partition_point(|&x| x <= target,),
+                "B = {}",
+                B
+            );
+        }
+
+        for n in (1..500).chain([B * (B + 1) * (B + 1) + 3,],) {
+            let input: Vec<i32,> = (0..n as i32).collect();
+            let layout = create_layout_stree::<_, B,>(&input,);
+
+            for (idx, &rank,) in layout.iter().take(n,).enumerate() {
+                assert_eq!(index_to_rank::<B,>(n, idx,), rank as usize, "B = {}", B);
+                assert_eq!(rank_to_index::<B,>(n, rank as usize,), idx, "B = {}", B);
+            }
+
+            let mut in_place = input.clone();
+            create_layout_stree_in_place::<_, B,>(&mut in_place,);
+            assert_eq!(in_place, layout, "n = {}, B = {}", n, B);
+
+            for threads in [1, 3, 8,] {
+                let parallel = create_layout_stree_parallel::<_, B,>(&input, threads,);
+                assert_eq!(
+                    parallel, layout,
+                    "n = {}, B = {}, threads = {}",
+                    n, B, threads
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_in_place_buffer() {
+        // With room for the padding, the keys stay in the buffer they were given in
+        for n in [1, 15, 16, 17, 1000,] {
+            let input: Vec<i32,> = (0..n).collect();
+
+            let mut data = Vec::with_capacity(input.len() + 15,);
+            data.extend_from_slice(&input,);
+            let ptr = data.as_ptr();
+
+            create_layout_stree_in_place::<_, 16,>(&mut data,);
+            assert_eq!(data.as_ptr(), ptr, "n = {}", n);
+            assert_eq!(data, create_layout_stree::<_, 16,>(&input,), "n = {}", n);
+        }
+    }
+
+    #[test]
+    fn test_widths_fuzzy() {
+        check_width::<4,>();
+        check_width::<8,>();
+        check_width::<16,>();
+        check_width::<32,>();
+        check_width::<64,>();
+    }
+
+    fn check_finger<const B: usize,>() {
+        let mut rng = rand::rng();
+
+        for n in [1, 5, B, B * (B + 1) + 3, 3000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..1000,),).collect();
+            data.push(i32::MAX,);
+            data.sort_unstable();
+            let n = data.len();
+            let layout = create_layout_stree::<_, B,>(&data,);
+
+            // Increasing queries, then queries in any order
+            let mut targets: Vec<i32,> =
+                (0..500).map(|_| rng.random_range(-5..1005,),).collect();
+            targets.sort_unstable();
+            targets.extend((0..500).map(|_| rng.random_range(-5..1005,),),);
+            targets.push(i32::MAX,);
+
+            let rank = |i: Option<usize,>| i.map_or(n, |i| index_to_rank::<B,>(n, i,),);
+
+            for backend in Backend::supported() {
+                let mut lower_finger = Finger::new();
+                let mut upper_finger = Finger::new();
+
+                for &target in &targets {
+                    assert_eq!(
+                        rank(lower_bound_finger_with::<_, B,>(
+                            backend,
+                            &layout,
+                            n,
+                            &target,
+                            &mut lower_finger,
+                        ),),
+                        data.partition_point(|&x| x < target,),
+                        "Lower bound mismatch for target {}, n = {}, B = {}, {:?}",
+                        target,
+                        n,
+                        B,
+                        backend
+                    );
+                    assert_eq!(
+                        rank(upper_bound_finger_with::<_, B,>(
+                            backend,
+                            &layout,
+                            n,
+                            &target,
+                            &mut upper_finger,
+                        ),),
+                        data.partition_point(|&x| x <= target,),
+                        "Upper bound mismatch for target {}, n = {}, B = {}, {:?}",
+                        target,
+                        n,
+                        B,
+                        backend
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_finger_fuzzy() {
+        check_finger::<4,>();
+        check_finger::<16,>();
+        check_finger::<64,>();
+    }
+
+    fn check_batch<const B: usize, const P: usize,>(layout: &[i32], targets: &[i32],) {
+        let mut results = vec![None; targets.len()];
+
+        for backend in Backend::supported() {
+            lower_bound_batch_with::<_, B, P,>(backend, layout, targets, &mut results,);
+            for (target, res,) in targets.iter().zip(&results,) {
+                assert_eq!(
+                    *res,
+                    lower_bound_with::<_, B,>(backend, layout, target,),
+                    "B = {}, P = {}, {:?}",
+                    B,
+                    P,
+                    backend
+                );
+            }
+        }
+
+        lower_bound_batch::<_, B, P,>(layout, targets, &mut results,);
+        for (target, res,) in targets.iter().zip(&results,) {
+            assert_eq!(
+                *res,
+                lower_bound::<_, B,>(layout, target,),
+                "B = {}, P = {}",
+                B,
+                P
+            );
+        }
+
+        upper_bound_batch::<_, B, P,>(layout, targets, &mut results,);
+        for (target, res,) in targets.iter().zip(&results,) {
+            assert_eq!(
+                *res,
+                upper_bound::<_, B,>(layout, target,),
+                "B = {}, P = {}",
+                B,
+                P
+            );
+        }
+    }
+
+    #[test]
+    fn test_bound_batch_fuzzy() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, 20, 300, 5000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..1000,),).collect();
+            data.sort_unstable();
+            let targets: Vec<i32,> =
+                (0..1000).map(|_| rng.random_range(-5..1005,),).collect();
+
+            let layout = create_layout_stree::<_, 16,>(&data,);
+            check_batch::<16, 1,>(&layout, &targets,);
+            check_batch::<16, 5,>(&layout, &targets,);
+            check_batch::<16, 16,>(&layout, &targets,);
+
+            let layout = create_layout_stree::<_, 4,>(&data,);
+            check_batch::<4, 8,>(&layout, &targets,);
+        }
+    }
+
+    fn check_sorted_batch<const B: usize,>() {
+        let mut rng = rand::rng();
+
+        for n in [0, 1, B, B * (B + 1) + 3, 3000,] {
+            let mut data: Vec<i32,> =
+                (0..n).map(|_| rng.random_range(0..100,),).collect();
+            data.push(i32::MAX,);
+            data.sort_unstable();
+            let n = data.len();
+            let layout = create_layout_stree::<_, B,>(&data,);
+
+            let mut targets: Vec<i32,> =
+                (0..500).map(|_| rng.random_range(-5..105,),).collect();
+            targets.push(i32::MAX,);
+            targets.sort_unstable();
+            let mut ranks = vec![0; targets.len()];
+
+            lower_bound_sorted_batch::<_, B,>(&layout, n, &targets, &mut ranks,);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(
+                    rank,
+                    data.partition_point(|x| x < target,),
+                    "n = {}, B = {}",
+                    n,
+                    B
+                );
+            }
+
+            upper_bound_sorted_batch::<_, B,>(&layout, n, &targets, &mut ranks,);
+            for (target, &rank,) in targets.iter().zip(&ranks,) {
+                assert_eq!(
+                    rank,
+                    data.partition_point(|x| x <= target,),
+                    "n = {}, B = {}",
+                    n,
+                    B
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_bound_sorted_batch_fuzzy() {
+        check_sorted_batch::<4,>();
+        check_sorted_batch::<16,>();
+        check_sorted_batch::<64,>();
+    }
+
+    fn check_count<const B: usize,>() {
+        let mut rng = rand::rng();
+
+        let mut data: Vec<i32,> = (0..2000).map(|_| rng.random_range(0..100,),).collect();
+        data.extend([i32::MAX, i32::MAX,],);
+        data.sort_unstable();
+        let n = data.len();
+        let layout = create_layout_stree::<_, B,>(&data,);
+
+        for target in (-2..102).chain([i32::MAX,],) {
+            assert_eq!(
+                equal_range::<_, B,>(&layout, n, &target,),
+                (
+                    data.partition_point(|&x| x < target,),
+                    data.partition_point(|&x| x <= target,),
+                )
+            );
+            assert_eq!(
+                count::<_, B,>(&layout, n, &target,),
+                data.iter().filter(|&&x| x == target,).count()
+            );
+        }
+
+        for _ in 0..1000 {
+            let (lo, hi,) = (rng.random_range(-2..102,), rng.random_range(-2..102,),);
+            let expected = data.iter().filter(|&&x| lo <= x && x < hi,).count();
+            assert_eq!(count_in_range::<_, B,>(&layout, n, &lo, &hi,), expected);
+        }
+    }
+
+    #[test]
+    fn test_count_fuzzy() {
+        check_count::<4,>();
+        check_count::<16,>();
+        check_count::<64,>();
+    }
 }
@@ -0,0 +1,312 @@
+use crate::{
+    backend::Backend,
+    basics,
+    eytzinger::{self, create_layout_eytzinger, Prefetch},
+    stree::{self, create_layout_stree, Key},
+};
+
+
+const BLOCK: usize = 16;
+
+
+// Static layout holding the bulk of the keys
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Base {
+    Eytzinger,
+    STree,
+}
+
+// The logical contents are the base keys, plus the delta, minus the tombstones. Each
+// tombstone deletes one occurrence of a base key, deleting a delta key removes it. The
+// base is searched with the kernels of the backend current when the index is built.
+pub struct UpdatableIndex<K: Key,> {
+    kind:       Base,
+    backend:    Backend,
+    threshold:  usize,
+    base:       Vec<K,>,
+    n:          usize,
+    delta:      Vec<K,>,
+    tombstones: Vec<K,>,
+}
+
+
+fn build<K: Key,>(kind: Base, data: &[K],) -> Vec<K,> {
+    match kind {
+        | Base::Eytzinger => create_layout_eytzinger(data,),
+        | Base::STree => create_layout_stree::<K, BLOCK,>(data,),
+    }
+}
+
+
+impl<K: Key,> UpdatableIndex<K,> {
+    // The base is rebuilt once the delta and the tombstones hold more than threshold keys
+    #[must_use]
+    pub fn new(data: &[K], kind: Base, threshold: usize,) -> Self {
+        assert!(data.is_sorted(), "the base keys must be sorted");
+
+        Self {
+            kind,
+            backend: Backend::current(),
+            threshold,
+            base: build(kind, data,),
+            n: data.len(),
+            delta: Vec::new(),
+            tombstones: Vec::new(),
+        }
+    }
+
+    // Same index searched with the kernels of backend
+    #[must_use]
+    pub fn with_backend(self, backend: Backend,) -> Self {
+        assert!(backend.is_supported(), "{:?} is not supported", backend);
+
+        Self { backend, ..self }
+    }
+
+    #[must_use]
+    pub fn backend(&self,) -> Backend {
+        self.backend
+    }
+
+    #[must_use]
+    pub fn len(&self,) -> usize {
+        self.n + self.delta.len() - self.tombstones.len()
+    }
+
+    #[cfg_attr(not(test), expect(unused))]
+    #[must_use]
+    pub fn is_empty(&self,) -> bool {
+        self.len() == 0
+    }
+
+    #[inline(always)]
+    fn base_lower_bound(&self, target: &K,) -> usize {
+        match self.kind {
+            | Base::Eytzinger => {
+                Prefetch::of(self.backend,).lower_bound_rank(&self.base, target,)
+            },
+            | Base::STree => stree::lower_bound_rank_with::<K, BLOCK,>(
+                self.backend,
+                &self.base,
+                self.n,
+                target,
+            ),
+        }
+    }
+
+    #[inline(always)]
+    fn base_upper_bound(&self, target: &K,) -> usize {
+        match self.kind {
+            | Base::Eytzinger => {
+                Prefetch::of(self.backend,).upper_bound_rank(&self.base, target,)
+            },
+            | Base::STree => stree::upper_bound_rank_with::<K, BLOCK,>(
+                self.backend,
+                &self.base,
+                self.n,
+                target,
+            ),
+        }
+    }
+
+    #[inline(always)]
+    fn base_count(&self, target: &K,) -> usize {
+        self.base_upper_bound(target,) - self.base_lower_bound(target,)
+    }
+
+    #[inline(always)]
+    fn base_index(&self, rank: usize,) -> usize {
+        match self.kind {
+            | Base::Eytzinger => eytzinger::rank_to_index(self.n, rank,),
+            | Base::STree => stree::rank_to_index::<BLOCK,>(self.n, rank,),
+        }
+    }
+
+    // Every tombstone is a base key, so the keys before target in the logical contents
+    // are those of the base and of the delta, minus the tombstones before target.
+    #[must_use]
+    #[inline(always)]
+    pub fn lower_bound(&self, target: &K,) -> usize {
+        self.base_lower_bound(target,) - basics::lower_bound(&self.tombstones, target,)
+            + basics::lower_bound(&self.delta, target,)
+    }
+
+    #[must_use]
+    #[inline(always)]
+    pub fn upper_bound(&self, target: &K,) -> usize {
+        self.base_upper_bound(target,) - basics::upper_bound(&self.tombstones, target,)
+            + basics::upper_bound(&self.delta, target,)
+    }
+
+    pub fn insert(&mut self, key: K,) {
+        // A deleted base key comes back by dropping its tombstone
+        let (lo, hi,) = basics::equal_range(&self.tombstones, &key,);
+
+        if lo < hi {
+            self.tombstones.remove(lo,);
+        } else {
+            let i = basics::upper_bound(&self.delta, &key,);
+            self.delta.insert(i, key,);
+        }
+
+        self.compact_if_needed();
+    }
+
+    // Removes one occurrence of key, returns false when there is none
+    pub fn remove(&mut self, key: &K,) -> bool {
+        let (lo, hi,) = basics::equal_range(&self.delta, key,);
+
+        if lo < hi {
+            self.delta.remove(lo,);
+            return true;
+        }
+
+        let (lo, hi,) = basics::equal_range(&self.tombstones, key,);
+        if self.base_count(key,) == hi - lo {
+            return false;
+        }
+
+        self.tombstones.insert(lo, *key,);
+        self.compact_if_needed();
+
+        true
+    }
+
+    fn compact_if_needed(&mut self,) {
+        if self.delta.len() + self.tombstones.len() > self.threshold {
+            self.compact();
+        }
+    }
+
+    // Rebuilds the base from the logical contents, with an empty delta and no tombstones
+    pub fn compact(&mut self,) {
+        let mut merged = Vec::with_capacity(self.len(),);
+
+        let mut delta = self.delta.iter().peekable();
+        let mut tombstones = self.tombstones.iter().peekable();
+
+        for rank in 0..self.n {
+            let key = self.base[self.base_index(rank,)];
+
+            if tombstones.next_if(|&&dead| dead == key,).is_some() {
+                continue;
+            }
+
+            while let Some(&inserted,) = delta.next_if(|&&inserted| inserted < key,) {
+                merged.push(inserted,);
+            }
+            merged.push(key,);
+        }
+        merged.extend(delta,);
+
+        self.base = build(self.kind, &merged,);
+        self.n = merged.len();
+        self.delta.clear();
+        self.tombstones.clear();
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn test_insert_remove_basic() {
+        let mut index = UpdatableIndex::new(&[10, 20, 20, 30,], Base::STree, 100,);
+
+        index.insert(25,);
+        index.insert(20,);
+        assert_eq!(index.len(), 6);
+        assert_eq!(index.lower_bound(&20,), 1);
+        assert_eq!(index.upper_bound(&20,), 4);
+        assert_eq!(index.lower_bound(&30,), 5);
+
+        // Two base occurrences and one delta occurrence of 20
+        assert!(index.remove(&20,));
+        assert!(index.remove(&20,));
+        assert!(index.remove(&20,));
+        assert!(!index.remove(&20,));
+        assert!(!index.remove(&40,));
+        assert_eq!(index.upper_bound(&20,), 1);
+
+        // Re-inserting a deleted base key drops its tombstone
+        index.insert(20,);
+        assert_eq!(index.tombstones.len(), 1);
+        assert_eq!((index.lower_bound(&20,), index.upper_bound(&20,)), (1, 2));
+
+        index.compact();
+        assert_eq!(index.len(), 4);
+        assert!(!index.is_empty());
+        assert!(index.delta.is_empty() && index.tombstones.is_empty());
+        assert_eq!(index.lower_bound(&25,), 2);
+        assert_eq!(index.lower_bound(&30,), 3);
+    }
+
+    #[test]
+    fn test_compaction_threshold() {
+        let data: Vec<i32,> = (0..100).collect();
+        let mut index = UpdatableIndex::new(&data, Base::Eytzinger, 4,);
+
+        for key in 0..4 {
+            index.insert(key,);
+        }
+        assert_eq!(index.delta.len(), 4);
+
+        index.insert(4,);
+        assert!(index.delta.is_empty());
+        assert_eq!(index.n, 105);
+    }
+
+    #[test]
+    fn test_bound_fuzzy() {
+        let mut rng = rand::rng();
+
+        for kind in [Base::Eytzinger, Base::STree,] {
+            for threshold in [0, 7, 64, 10_000,] {
+                let mut logical: Vec<i32,> =
+                    (0..1000).map(|_| rng.random_range(0..200,),).collect();
+                logical.sort_unstable();
+                let mut index = UpdatableIndex::new(&logical, kind, threshold,);
+
+                for _ in 0..3000 {
+                    let key = rng.random_range(0..210,);
+
+                    if rng.random::<bool>() {
+                        index.insert(key,);
+                        let i = logical.partition_point(|&x| x <= key,);
+                        logical.insert(i, key,);
+                    } else {
+                        let i = logical.partition_point(|&x| x < key,);
+                        let present = logical.get(i,) == Some(&key,);
+                        if present {
+                            logical.remove(i,);
+                        }
+                        assert_eq!(index.remove(&key,), present);
+                    }
+
+                    let target = rng.random_range(-5..215,);
+                    assert_eq!(index.len(), logical.len());
+                    assert_eq!(
+                        index.lower_bound(&target,),
+                        basics::lower_bound(&logical, &target,),
+                        "Lower bound mismatch for target {}, {:?}, threshold {}",
+                        target,
+                        kind,
+                        threshold
+                    );
+                    assert_eq!(
+                        index.upper_bound(&target,),
+                        basics::upper_bound(&logical, &target,),
+                        "Upper bound mismatch for target {}, {:?}, threshold {}",
+                        target,
+                        kind,
+                        threshold
+                    );
+                }
+            }
+        }
+    }
+}
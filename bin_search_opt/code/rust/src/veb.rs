@@ -0,0 +1,286 @@
+use std::borrow::Borrow;
+
+use crate::eytzinger::index_to_rank;
+
+
+const MAX_HEIGHT: usize = usize::BITS as usize;
+
+
+// The van Emde Boas layout stores the same complete tree as the Eytzinger layout. A
+// tree of height h is cut at mid height: its top tree of h / 2 levels is stored first,
+// followed by every bottom tree hanging from it, left to right, each laid out the same
+// way recursively.
+//
+// A node at depth d is the root of a bottom tree for exactly one cut, made in a tree
+// rooted at its ancestor of depth top_depth. Following the cuts down from the whole
+// tree, this returns that ancestor depth, the height of the top tree and the height of
+// the bottom trees.
+#[inline(always)]
+fn cut(height: usize, depth: usize,) -> (usize, usize, usize,) {
+    let (mut top_depth, mut height,) = (0, height,);
+
+    loop {
+        let top = height / 2;
+        let bottom = height - top;
+
+        if depth == top_depth + top {
+            return (top_depth, top, bottom,);
+        }
+        if depth < top_depth + top {
+            height = top;
+        } else {
+            top_depth += top;
+            height = bottom;
+        }
+    }
+}
+
+// Position of the node i (1-indexed, breadth first), knowing the position root of the
+// root of the tree in which it is the root of a bottom tree. Only the last level of the
+// whole tree can be incomplete, so the bottom trees on its left all have
+// 2^(bottom - 1) - 1 nodes, plus the nodes they have on that last level.
+#[inline(always)]
+fn position(n: usize, i: usize, top: usize, bottom: usize, root: usize,) -> usize {
+    let first = (i >> top) << top;
+    let j = i - first;
+
+    let last_level = (n + 1).saturating_sub(first << (bottom - 1),);
+
+    root + (1 << top) - 1
+        + j * ((1 << (bottom - 1)) - 1)
+        + std::cmp::min(last_level, j << (bottom - 1),)
+}
+
+
+#[must_use]
+pub fn create_layout_veb<T: Clone,>(data: &[T],) -> Vec<T,> {
+    let n = data.len();
+    if n == 0 {
+        return Vec::new();
+    }
+
+    let mut veb_data = vec![data[0].clone(); n];
+    fn aux<T: Clone,>(
+        source: &[T],
+        dest: &mut [T],
+        root: usize,
+        height: usize,
+        pos: &mut usize,
+    ) {
+        let n = source.len();
+        if root > n {
+            return;
+        }
+
+        if height == 1 {
+            dest[*pos] = source[index_to_rank(n, root - 1,)].clone();
+            *pos += 1;
+            return;
+        }
+
+        let top = height / 2;
+        let bottom = height - top;
+
+        aux(source, dest, root, top, pos,);
+        for j in 0..1 << top {
+            aux(source, dest, (root << top) + j, bottom, pos,);
+        }
+    }
+
+    let height = (usize::BITS - n.leading_zeros()) as usize;
+    aux(data, &mut veb_data, 1, height, &mut 0,);
+    veb_data
+}
+
+
+macro_rules! impl_bound_veb {
+    ($(#[$attr:meta])* $name:ident, $go_left:ident) => {
+        $(#[$attr])*
+        #[must_use]
+        #[inline(always)]
+        pub fn $name<B, T,>(data: &[B], target: &T,) -> Option<usize,>
+        where
+            B: Borrow<T,>,
+            T: PartialOrd + ?Sized,
+        {
+            let n = data.len();
+            if n == 0 {
+                return None;
+            }
+
+            let height = (usize::BITS - n.leading_zeros()) as usize;
+
+            // Positions of the nodes on the search path, by depth
+            let mut path = [0; MAX_HEIGHT];
+
+            let mut res: Option<usize,> = None;
+            let mut i = 1;
+            let mut depth = 0;
+
+            while i <= n {
+                let pos = if depth == 0 {
+                    0
+                } else {
+                    let (top_depth, top, bottom,) = cut(height, depth,);
+                    position(n, i, top, bottom, path[top_depth],)
+                };
+                path[depth] = pos;
+
+                if data[pos].borrow().$go_left(target,) {
+                    res = Some(pos,);
+                    i *= 2;
+                } else {
+                    i = 2 * i + 1;
+                }
+
+                depth += 1;
+            }
+
+            res
+        }
+    };
+}
+
+impl_bound_veb!(lower_bound, ge);
+impl_bound_veb!(
+    #[cfg_attr(not(test), expect(unused))]
+    upper_bound,
+    gt
+);
+
+
+#[cfg(test)]
+mod tests {
+    use rand::RngExt;
+
+    use super::*;
+
+    #[test]
+    fn test_veb_layout_structure() {
+        // Input: Sorted array [1, 2, 3, 4, 5, 6, 7]
+        // Tree View:
+        //       4
+        //     /   \
+        //   2       6
+        //  / \     / \
+        // 1   3   5   7
+        //
+        // Top tree [4], then the bottom trees [2, 1, 3] and [6, 5, 7]
+
+        let input = vec![1, 2, 3, 4, 5, 6, 7];
+        let layout = create_layout_veb(&input,);
+
+        assert_eq!(layout, vec![4, 2, 1, 3, 6, 5, 7]);
+
+        // Height 4: top tree [8, 4, 12], then 4 bottom trees of height 2
+        let input: Vec<i32,> = (1..16).collect();
+        let layout = create_layout_veb(&input,);
+
+        assert_eq!(layout, vec![8, 4, 12, 2, 1, 3, 6, 5, 7, 10, 9, 11, 14, 13, 15]);
+    }
+
+    #[test]
+    fn test_veb_layout_imperfect_tree() {
+        // Input: [1, 2, 3, 4, 5] (Not a perfect power of 2)
+        // Tree:
+        //      4
+        //    /   \
+        //   2     5
+        //  / \
+        // 1   3
+        //
+        // Top tree [4], then the bottom trees [2, 1, 3] and [5]
+
+        let input = vec![1, 2, 3, 4, 5];
+        let layout = create_layout_veb(&input,);
+
+        assert_eq!(layout, vec![4, 2, 1, 3, 5]);
+    }
+
+    #[test]
+    fn test_lower_bound_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+        let data = create_layout_veb(&data,);
+
+        // 1. Target present multiple times -> First occurrence
+        assert_eq!(data[lower_bound(&data, &20).unwrap()], 20);
+
+        // 2. Target present once
+        assert_eq!(data[lower_bound(&data, &30).unwrap()], 30);
+
+        // 3. Target missing (insert in middle)
+        assert_eq!(data[lower_bound(&data, &25).unwrap()], 30);
+
+        // 4. Target smaller than all (insert at start)
+        assert_eq!(data[lower_bound(&data, &5).unwrap()], 10);
+
+        // 5. Target larger than all (insert at end)
+        assert_eq!(lower_bound(&data, &50), None);
+    }
+
+    #[test]
+    fn test_upper_bound_correctness() {
+        let data = vec![10, 20, 20, 20, 30, 40];
+        let data = create_layout_veb(&data,);
+
+        // 1. Target present multiple times -> First element STRICTLY greater
+        assert_eq!(data[upper_bound(&data, &20).unwrap()], 30);
+
+        // 2. Target present once
+        assert_eq!(data[upper_bound(&data, &30).unwrap()], 40);
+
+        // 3. Target missing (behavior identical to lower_bound for gaps)
+        assert_eq!(data[upper_bound(&data, &25).unwrap()], 30);
+
+        // 4. Target larger than all (insert at end)
+        assert_eq!(upper_bound(&data, &40), None);
+    }
+
+    #[test]
+    fn test_every_size() {
+        // With distinct keys, searching each key must land on it
+        for n in 1..1100 {
+            let input: Vec<usize,> = (0..n).collect();
+            let layout = create_layout_veb(&input,);
+
+            let mut sorted = layout.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, input, "n = {}", n);
+
+            for target in 0..n {
+                assert_eq!(
+                    lower_bound(&layout, &target,).map(|i| layout[i],),
+                    Some(target,)
+                );
+            }
+            assert_eq!(lower_bound(&layout, &n,), None);
+        }
+    }
+
+    #[test]
+    fn test_bound_fuzzy() {
+        let mut rng = rand::rng();
+
+        let n = 2000;
+        let mut data: Vec<i32,> = (0..n).map(|_| rng.random_range(0..100,),).collect();
+        data.sort_unstable();
+
+        let layout = create_layout_veb(&data,);
+
+        for _ in 0..1000 {
+            let target = rng.random_range(0..110,);
+
+            let idx = data.partition_point(|&x| x < target,);
+            assert_eq!(
+                lower_bound(&layout, &target,).map(|i| layout[i],),
+                data.get(idx,).copied()
+            );
+
+            let idx = data.partition_point(|&x| x <= target,);
+            assert_eq!(
+                upper_bound(&layout, &target,).map(|i| layout[i],),
+                data.get(idx,).copied()
+            );
+        }
+    }
+}